@@ -0,0 +1,66 @@
+//! Compares the small-integer fast path of `Integer` against the checked
+//! `i64` arithmetic the interpreter used before big integers. Run with
+//! `cargo bench`.
+
+extern crate katlang;
+
+use katlang::integer::Integer;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: i64 = 10_000_000;
+
+fn time(name: &str, iterations: i64, func: impl Fn() -> String) -> Duration {
+    let now = Instant::now();
+    let result = func();
+    let elapsed = now.elapsed();
+    println!(
+        "{: <24} {: >8.2} ns/iter (result {})",
+        name,
+        elapsed.as_secs_f64() * 1e9 / iterations as f64,
+        result
+    );
+    elapsed
+}
+
+fn main() {
+    let native = time("i64 add/mul/mod", ITERATIONS, || {
+        let mut acc = 1i64;
+        for i in 1..ITERATIONS {
+            acc = acc
+                .checked_mul(3)
+                .and_then(|v| v.checked_add(black_box(i)))
+                .unwrap()
+                .rem_euclid(1_000_003);
+        }
+        acc.to_string()
+    });
+    let small = time("Integer add/mul/mod", ITERATIONS, || {
+        let mut acc = Integer::from(1);
+        let three = Integer::from(3);
+        let modulus = Integer::from(1_000_003);
+        for i in 1..ITERATIONS {
+            acc = acc
+                .mul(&three)
+                .add(&Integer::from(black_box(i)))
+                .floor_mod(&modulus)
+                .unwrap();
+        }
+        acc.to_string()
+    });
+    println!(
+        "Integer / i64: {:.2}x",
+        small.as_secs_f64() / native.as_secs_f64()
+    );
+
+    time("Integer fibonacci (big)", 10_000, || {
+        let mut a = Integer::from(1);
+        let mut b = Integer::from(1);
+        for _ in 0..10_000 {
+            let next = a.add(&b);
+            a = b;
+            b = next;
+        }
+        b.to_string().len().to_string() + " digits"
+    });
+}
@@ -0,0 +1,36 @@
+//! Runs whole programs through `run`, to catch slowdowns in the interpreter
+//! that benchmarks of single parts miss. Run with `cargo bench`.
+
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::{run, Limits};
+use std::time::{Duration, Instant};
+
+/// Each program, with what it exercises.
+const PROGRAMS: &[(&str, &str)] = &[
+    ("grow a list", "()50000#(1)+"),
+    ("map a list", "100000r&2*1+"),
+    ("filter a list", "100000r⊆2%"),
+    ("sort a list", "100000r&7*1000%$↑"),
+    ("side stack", "1 100000#p1+$~"),
+    ("count down", "1000000:¡1-:$"),
+    ("fibonacci", "1:10000#;+x$_"),
+];
+
+fn main() {
+    for &(name, code) in PROGRAMS {
+        let now = Instant::now();
+        let output = run(code, "", &Limits::default()).unwrap();
+        let elapsed = now.elapsed();
+        let mut result = render_stack(&output.stack);
+        result.truncate(20);
+        println!(
+            "{: <16} {: >10.2} ms (result {})",
+            name,
+            elapsed.as_secs_f64() * 1e3,
+            result
+        );
+        assert!(elapsed < Duration::from_secs(10), "{} is too slow", name);
+    }
+}
@@ -0,0 +1,899 @@
+//! The table of builtin commands. The parser, the `Debug` output of commands
+//! and the command reference in spec.md are all driven by `BUILTINS`.
+
+use interpreter::CatValue::{self, *};
+use spec::{CatCommand, Rotation};
+use std::fmt::Write;
+use {run, Limits};
+
+/// How a command's glyph affects the parsing of the code after it. The
+/// function builds the parsed command.
+#[derive(Clone, Copy, Debug)]
+pub enum ParseRule {
+    /// The glyph stands alone.
+    Plain(fn() -> CatCommand),
+    /// The glyph is followed by an implicit block.
+    ImplicitBlock(fn() -> CatCommand),
+    /// The glyph is followed by a variable name.
+    ReadsVariable(fn(char) -> CatCommand),
+    /// The glyph quotes the command after it.
+    QuotesCommand,
+}
+
+/// How a command treats lists in place of its usual arguments.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vectorization {
+    /// Lists aren't treated specially.
+    None,
+    /// The command is applied to each item of a list.
+    Each,
+    /// The command is applied to each item of a list argument. With two
+    /// lists, the result is a list of lists.
+    Either,
+    /// The comparison is applied to each item if exactly one argument is a
+    /// list.
+    OneSide,
+}
+
+/// An example program and what it leaves behind.
+#[derive(Clone, Copy, Debug)]
+pub struct Example {
+    pub code: &'static str,
+    /// Fed to the program as stdin.
+    pub input: &'static str,
+    /// The final stack as rendered by `render_stack`.
+    pub stack: &'static str,
+    /// Everything the program writes.
+    pub output: &'static str,
+}
+
+impl Example {
+    const fn new(code: &'static str, stack: &'static str) -> Example {
+        Example {
+            code,
+            input: "",
+            stack,
+            output: "",
+        }
+    }
+
+    const fn prints(self, output: &'static str) -> Example {
+        Example { output, ..self }
+    }
+
+    const fn reads(self, input: &'static str) -> Example {
+        Example { input, ..self }
+    }
+}
+
+/// Describes a builtin command.
+#[derive(Debug)]
+pub struct CommandInfo {
+    pub glyph: char,
+    pub name: &'static str,
+    /// The name of the command in verbose source, eg. `dup`.
+    pub word: &'static str,
+    /// The stack effect, eg. `a b -- c`. Values used by the functions the
+    /// command executes aren't included.
+    pub effect: &'static str,
+    pub rule: ParseRule,
+    pub vectorization: Vectorization,
+    /// A one-paragraph description.
+    pub help: &'static str,
+    /// Further notes, each rendered as a sub-bullet.
+    pub details: &'static [&'static str],
+    pub examples: &'static [Example],
+}
+
+impl CommandInfo {
+    /// How many values the command pops, according to its stack effect.
+    pub fn arity(&self) -> usize {
+        self.effect
+            .split("--")
+            .next()
+            .map_or(0, |inputs| inputs.split_whitespace().count())
+    }
+
+    /// Whether this entry describes `command`.
+    fn describes(&self, command: &CatCommand) -> bool {
+        match self.rule {
+            ParseRule::Plain(make) | ParseRule::ImplicitBlock(make) => make() == *command,
+            ParseRule::ReadsVariable(make) => match command {
+                CatCommand::PushVariable(c) | CatCommand::PopVariable(c, false) => {
+                    make(*c) == *command
+                }
+                _ => false,
+            },
+            ParseRule::QuotesCommand => matches!(command, CatCommand::CreateCommand(_)),
+        }
+    }
+}
+
+/// Finds the builtin command with the given glyph.
+pub fn by_glyph(glyph: char) -> Option<&'static CommandInfo> {
+    BUILTINS.iter().find(|info| info.glyph == glyph)
+}
+
+/// Finds the builtin command with the given verbose name.
+pub fn by_word(word: &str) -> Option<&'static CommandInfo> {
+    BUILTINS.iter().find(|info| info.word == word)
+}
+
+/// Finds the table entry a parsed command came from. Literals, blocks,
+/// natives and executions of known variables have no entry.
+pub fn info(command: &CatCommand) -> Option<&'static CommandInfo> {
+    BUILTINS.iter().find(|info| info.describes(command))
+}
+
+/// Wraps code in a Markdown code span.
+fn code_span(code: &str) -> String {
+    if code.contains('`') {
+        format!("`` {} ``", code)
+    } else {
+        format!("`{}`", code)
+    }
+}
+
+/// Renders the command list of spec.md as Markdown.
+pub fn reference() -> String {
+    let mut out = String::new();
+    for info in BUILTINS {
+        writeln!(
+            out,
+            "- {} ({}, {}) {}: {}",
+            code_span(&info.glyph.to_string()),
+            info.name,
+            code_span(info.word),
+            code_span(info.effect),
+            info.help
+        )
+        .unwrap();
+        let vectorization = match info.vectorization {
+            Vectorization::None => None,
+            Vectorization::Each => Some("If the value is a list, it's looped over."),
+            Vectorization::Either => Some(
+                "If either value is a list, it's looped over.",
+            ),
+            Vectorization::OneSide => Some(
+                "If exactly one value is a list, the comparison is applied to each item. Two lists are compared as whole values.",
+            ),
+        };
+        if let Some(text) = vectorization {
+            writeln!(out, "  - {}", text).unwrap();
+        }
+        match info.rule {
+            ParseRule::ImplicitBlock(_) => writeln!(out, "  - Parsing rule: implicit block."),
+            ParseRule::ReadsVariable(_) => writeln!(
+                out,
+                "  - Parsing rule: reads the next character as the variable name."
+            ),
+            ParseRule::Plain(_) | ParseRule::QuotesCommand => Ok(()),
+        }
+        .unwrap();
+        for detail in info.details {
+            writeln!(out, "  - {}", detail).unwrap();
+        }
+        for example in info.examples {
+            write!(out, "  - Eg. {}", code_span(example.code)).unwrap();
+            if !example.input.is_empty() {
+                write!(out, " with the input {}", code_span(&escape(example.input))).unwrap();
+            }
+            if example.stack.is_empty() {
+                write!(out, " -> empty stack").unwrap();
+            } else {
+                write!(out, " -> {}", code_span(example.stack)).unwrap();
+            }
+            if !example.output.is_empty() {
+                write!(out, ", prints {}", code_span(&escape(example.output))).unwrap();
+            }
+            writeln!(out).unwrap();
+        }
+    }
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\n', "\\n")
+}
+
+/// Renders a stack the way the documentation writes values: lists in
+/// parentheses, negative numbers with a high minus and quoted commands with
+/// a backtick.
+pub fn render_stack(values: &[CatValue]) -> String {
+    values
+        .iter()
+        .map(render_value)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn render_value(value: &CatValue) -> String {
+    match value {
+        VInteger(v) => v.to_string().replace('-', "¯"),
+        VFloat(v) => format!("{:?}", v).replace('-', "¯"),
+        VString(v) => format!("{:?}", v),
+        VStack(v) => format!("({})", render_stack(v)),
+        VCommand(c) => match info(&c.kind) {
+            Some(info) => format!("`{}", info.glyph),
+            None => format!("{:?}", c),
+        },
+    }
+}
+
+/// Runs every example in `BUILTINS` and describes the ones that don't
+/// produce the documented result.
+pub fn check_examples() -> Vec<String> {
+    let limits = Limits {
+        max_steps: Some(100_000),
+        ..Limits::default()
+    };
+    let mut failures = Vec::new();
+    for info in BUILTINS {
+        for example in info.examples {
+            let result = match run(example.code, example.input, &limits) {
+                Ok(result) => result,
+                Err(e) => {
+                    failures.push(format!("{} ({}): {}", example.code, info.name, e));
+                    continue;
+                }
+            };
+            let stack = render_stack(&result.stack);
+            if stack != example.stack || result.output != example.output {
+                failures.push(format!(
+                    "{} ({}): expected stack {:?} and output {:?}, got {:?} and {:?}",
+                    example.code, info.name, example.stack, example.output, stack, result.output
+                ));
+            }
+        }
+    }
+    failures
+}
+
+pub static BUILTINS: &[CommandInfo] = &[
+    CommandInfo {
+        glyph: '`',
+        name: "CreateCommand",
+        word: "quote",
+        effect: "-- f",
+        rule: ParseRule::QuotesCommand,
+        vectorization: Vectorization::None,
+        help: "Quotes the builtin directly after this command, pushing the function to the stack instead of executing it.",
+        details: &[],
+        examples: &[Example::new("`+", "`+"), Example::new("1 2`+!", "3")],
+    },
+    CommandInfo {
+        glyph: '+',
+        name: "Add",
+        word: "add",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Add),
+        vectorization: Vectorization::Either,
+        help: "Pops two values and pushes their sum on the stack.",
+        details: &[
+            "If either value is a string, it's concatenated with the other value (which is coerced to a string implicitly).",
+            "Two lists are concatenated instead of looped over.",
+        ],
+        examples: &[
+            Example::new("20 31+", "51"),
+            Example::new("(1 2 3)1+", "(2 3 4)"),
+            Example::new("\"hi\"1+", "\"hi1\""),
+            Example::new("(1 2)(3 4)+", "(1 2 3 4)"),
+        ],
+    },
+    CommandInfo {
+        glyph: '-',
+        name: "Subtract",
+        word: "sub",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Subtract),
+        vectorization: Vectorization::Either,
+        help: "Pops `b` and `a` and pushes `a - b`.",
+        details: &[],
+        examples: &[
+            Example::new("10 3-", "7"),
+            Example::new("10(1 2 3)-", "(9 8 7)"),
+        ],
+    },
+    CommandInfo {
+        glyph: '*',
+        name: "Multiply",
+        word: "mul",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Multiply),
+        vectorization: Vectorization::Either,
+        help: "Pops two values and multiplies them. Errors if one of the values isn't a number.",
+        details: &[],
+        examples: &[
+            Example::new("6 7*", "42"),
+            Example::new("(1 2 3)2*", "(2 4 6)"),
+            Example::new("(1 2)(3 4)*", "((3 6) (4 8))"),
+        ],
+    },
+    CommandInfo {
+        glyph: '/',
+        name: "Divide",
+        word: "div",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Divide),
+        vectorization: Vectorization::Either,
+        help: "Pops `b` and `a` and pushes `a / b`. Division of two integers is rounded towards negative infinity and errors on division by zero.",
+        details: &[],
+        examples: &[Example::new("¯7 2/", "¯4"), Example::new("7·2/", "3.5")],
+    },
+    CommandInfo {
+        glyph: '%',
+        name: "Modulo",
+        word: "mod",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Modulo),
+        vectorization: Vectorization::Either,
+        help: "Pops `b` and `a` and pushes the remainder of `a / b`. The result has the sign of `b`.",
+        details: &[],
+        examples: &[Example::new("¯7 2%", "1"), Example::new("7 ¯2%", "¯1")],
+    },
+    CommandInfo {
+        glyph: '^',
+        name: "Power",
+        word: "pow",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Power),
+        vectorization: Vectorization::Either,
+        help: "Pops `b` and `a` and pushes `a` raised to the power `b`. An integer raised to a negative integer power produces a float. Errors if `b` is too large to compute.",
+        details: &[],
+        examples: &[Example::new("2 10^", "1024"), Example::new("2 ¯1^", "0.5")],
+    },
+    CommandInfo {
+        glyph: '±',
+        name: "Negate",
+        word: "neg",
+        effect: "a -- b",
+        rule: ParseRule::Plain(|| CatCommand::Negate),
+        vectorization: Vectorization::Each,
+        help: "Negates the top value.",
+        details: &[],
+        examples: &[Example::new("5±", "¯5"), Example::new("(1 ¯2)±", "(¯1 2)")],
+    },
+    CommandInfo {
+        glyph: '¦',
+        name: "Abs",
+        word: "abs",
+        effect: "a -- b",
+        rule: ParseRule::Plain(|| CatCommand::Abs),
+        vectorization: Vectorization::Each,
+        help: "Replaces the top value with its absolute value.",
+        details: &[],
+        examples: &[Example::new("¯5¦", "5")],
+    },
+    CommandInfo {
+        glyph: '§',
+        name: "Sign",
+        word: "sign",
+        effect: "a -- b",
+        rule: ParseRule::Plain(|| CatCommand::Sign),
+        vectorization: Vectorization::Each,
+        help: "Replaces the top value with `¯1`, `0` or `1` depending on its sign.",
+        details: &[],
+        examples: &[Example::new("(¯3 0 7)§", "(¯1 0 1)")],
+    },
+    CommandInfo {
+        glyph: '√',
+        name: "SquareRoot",
+        word: "sqrt",
+        effect: "a -- b",
+        rule: ParseRule::Plain(|| CatCommand::SquareRoot),
+        vectorization: Vectorization::Each,
+        help: "Replaces the top value with its square root as a float.",
+        details: &[],
+        examples: &[
+            Example::new("9√", "3.0"),
+            Example::new("2√", "1.4142135623730951"),
+        ],
+    },
+    CommandInfo {
+        glyph: '·',
+        name: "ToFloat",
+        word: "float",
+        effect: "a -- b",
+        rule: ParseRule::Plain(|| CatCommand::ToFloat),
+        vectorization: Vectorization::Each,
+        help: "Pops a value and coerces it to a float. Strings are parsed.",
+        details: &[],
+        examples: &[Example::new("3·", "3.0"), Example::new("\"2.5\"·", "2.5")],
+    },
+    CommandInfo {
+        glyph: '⌊',
+        name: "Min",
+        word: "min",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Min),
+        vectorization: Vectorization::Either,
+        help: "Pops two values and pushes the smaller one.",
+        details: &[],
+        examples: &[Example::new("3 5⌊", "3"), Example::new("3(1 5)⌊", "(1 3)")],
+    },
+    CommandInfo {
+        glyph: '⌈',
+        name: "Max",
+        word: "max",
+        effect: "a b -- c",
+        rule: ParseRule::Plain(|| CatCommand::Max),
+        vectorization: Vectorization::Either,
+        help: "Pops two values and pushes the larger one.",
+        details: &[],
+        examples: &[Example::new("3 5⌈", "5")],
+    },
+    CommandInfo {
+        glyph: 'R',
+        name: "ReadLine",
+        word: "read",
+        effect: "-- s",
+        rule: ParseRule::Plain(|| CatCommand::ReadLine),
+        vectorization: Vectorization::None,
+        help: "Reads a line from stdin (without the newline) and pushes it to the stack. Errors on EOF.",
+        details: &[],
+        examples: &[Example::new("R", "\"hello\"").reads("hello\n")],
+    },
+    CommandInfo {
+        glyph: 'W',
+        name: "WriteLine",
+        word: "writeln",
+        effect: "a --",
+        rule: ParseRule::Plain(|| CatCommand::WriteLine),
+        vectorization: Vectorization::None,
+        help: "Pops a value, coerces it to a string and writes it to stdout (with a following newline).",
+        details: &[],
+        examples: &[Example::new("1W(1 2)W", "").prints("1\n[1 2]\n")],
+    },
+    CommandInfo {
+        glyph: 'w',
+        name: "Write",
+        word: "write",
+        effect: "a --",
+        rule: ParseRule::Plain(|| CatCommand::Write),
+        vectorization: Vectorization::None,
+        help: "Pops a value, coerces it to a string and writes it to stdout (without a following newline).",
+        details: &[],
+        examples: &[Example::new("1w2w", "").prints("12")],
+    },
+    CommandInfo {
+        glyph: '&',
+        name: "Map",
+        word: "map",
+        effect: "list f -- list",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::Map),
+        vectorization: Vectorization::None,
+        help: "Pops a function, and then a list or a string. Applies the function to each item separately, collecting the top item of the stack after each iteration.",
+        details: &[],
+        examples: &[
+            Example::new("(1 2 3)&1+2*", "(4 6 8)"),
+            Example::new("\"abc\"&:+", "(\"aa\" \"bb\" \"cc\")"),
+        ],
+    },
+    CommandInfo {
+        glyph: '@',
+        name: "ForEach",
+        word: "each",
+        effect: "list f --",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::ForEach),
+        vectorization: Vectorization::None,
+        help: "Same as map, but does not collect the values.",
+        details: &[],
+        examples: &[
+            Example::new("(1 2 3)@W", "").prints("1\n2\n3\n"),
+            Example::new("0(1 2 3)@+", "6"),
+        ],
+    },
+    CommandInfo {
+        glyph: '⊆',
+        name: "Filter",
+        word: "filter",
+        effect: "list f -- list",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::Filter),
+        vectorization: Vectorization::None,
+        help: "Pops a function, and then a list or a string. Applies the function to each item separately and keeps the items for which the top item of the stack is true afterwards. Filtering a string produces a string.",
+        details: &[],
+        examples: &[
+            Example::new("(1 2 3 4 5)⊆2%", "(1 3 5)"),
+            Example::new("\"Hello\"⊆\"l\"=N", "\"Heo\""),
+        ],
+    },
+    CommandInfo {
+        glyph: ',',
+        name: "Fold",
+        word: "fold",
+        effect: "list f -- a",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::Fold),
+        vectorization: Vectorization::None,
+        help: "Pops a function, and then a list or a string. Pushes the first item, then pushes each following item and applies the function, folding the list from the left. Errors on an empty list.",
+        details: &[],
+        examples: &[Example::new("(1 2 3 4),+", "10")],
+    },
+    CommandInfo {
+        glyph: '\\',
+        name: "Scan",
+        word: "scan",
+        effect: "list f -- list",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::Scan),
+        vectorization: Vectorization::None,
+        help: "Same as fold, but collects the intermediate results into a list, starting with the first item. An empty list produces an empty list.",
+        details: &[],
+        examples: &[Example::new("(1 2 3 4)\\+", "(1 3 6 10)")],
+    },
+    CommandInfo {
+        glyph: '↑',
+        name: "Sort",
+        word: "sort",
+        effect: "list -- list",
+        rule: ParseRule::Plain(|| CatCommand::Sort),
+        vectorization: Vectorization::None,
+        help: "Pops a list or a string and sorts it in ascending order, as defined in [Ordering](#ordering). Sorting a string sorts its characters.",
+        details: &[],
+        examples: &[
+            Example::new("(3 1 2)↑", "(1 2 3)"),
+            Example::new("\"hello\"↑", "\"ehllo\""),
+        ],
+    },
+    CommandInfo {
+        glyph: 'B',
+        name: "SortBy",
+        word: "sort_by",
+        effect: "list f -- list",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::SortBy),
+        vectorization: Vectorization::None,
+        help: "Pops a function, and then a list or a string. Applies the function to each item, like map, and sorts the items by the results. The sort is stable.",
+        details: &[],
+        examples: &[Example::new("(3 1 2)B±", "(3 2 1)")],
+    },
+    CommandInfo {
+        glyph: '⊂',
+        name: "Group",
+        word: "group",
+        effect: "list -- list",
+        rule: ParseRule::Plain(|| CatCommand::Group),
+        vectorization: Vectorization::None,
+        help: "Pops a list or a string and groups runs of consecutive equal items into lists. The runs of a string are strings. Sort first to group all equal items together.",
+        details: &[],
+        examples: &[
+            Example::new("(1 1 2 1)⊂", "((1 1) (2) (1))"),
+            Example::new("\"mississippi\"↑⊂", "(\"iiii\" \"m\" \"pp\" \"ssss\")"),
+        ],
+    },
+    CommandInfo {
+        glyph: '¢',
+        name: "Count",
+        word: "count",
+        effect: "list a -- n",
+        rule: ParseRule::Plain(|| CatCommand::Count),
+        vectorization: Vectorization::None,
+        help: "Pops a value, and then a list or a string. Pushes the number of items equal to the value.",
+        details: &[],
+        examples: &[Example::new("\"hello\"\"l\"¢", "2")],
+    },
+    CommandInfo {
+        glyph: 'U',
+        name: "Unique",
+        word: "unique",
+        effect: "list -- list",
+        rule: ParseRule::Plain(|| CatCommand::Unique),
+        vectorization: Vectorization::None,
+        help: "Pops a list or a string and removes duplicate items, keeping the first occurrence of each.",
+        details: &[],
+        examples: &[Example::new("(3 1 3 2 1)U", "(3 1 2)")],
+    },
+    CommandInfo {
+        glyph: '#',
+        name: "Repeat",
+        word: "times",
+        effect: "n f --",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::Repeat),
+        vectorization: Vectorization::Each,
+        help: "Pops a function and a value. Coerces the value to an integer and repeats the function that many times.",
+        details: &[],
+        examples: &[Example::new("1 10#2*", "1024")],
+    },
+    CommandInfo {
+        glyph: '?',
+        name: "If",
+        word: "if",
+        effect: "cond f --",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::If),
+        vectorization: Vectorization::None,
+        help: "Pops a function and a condition. Executes the function if the condition is true.",
+        details: &[],
+        examples: &[
+            Example::new("1?\"yes\"", "\"yes\""),
+            Example::new("0?\"yes\"", ""),
+        ],
+    },
+    CommandInfo {
+        glyph: '|',
+        name: "IfElse",
+        word: "if_else",
+        effect: "cond then else --",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::IfElse),
+        vectorization: Vectorization::None,
+        help: "Pops an else function, a then function and a condition. Executes the then function if the condition is true, otherwise the else function. The implicit block is the else function.",
+        details: &[],
+        examples: &[Example::new("0[\"yes\"]|\"no\"", "\"no\"")],
+    },
+    CommandInfo {
+        glyph: '¡',
+        name: "While",
+        word: "while",
+        effect: "cond f --",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::While),
+        vectorization: Vectorization::None,
+        help: "Pops a function. Then repeatedly pops a condition and executes the function as long as the condition is true. The function is responsible for leaving the next condition on the stack.",
+        details: &[],
+        examples: &[
+            Example::new("3:¡:W1-:$", "0").prints("3\n2\n1\n"),
+            Example::new("1 1 0¡:W1$", "1 1"),
+        ],
+    },
+    CommandInfo {
+        glyph: '¿',
+        name: "Until",
+        word: "until",
+        effect: "cond f --",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::Until),
+        vectorization: Vectorization::None,
+        help: "Same as while, but executes the function as long as the condition is false.",
+        details: &[],
+        examples: &[Example::new("0 0¿1+:5=$", "5")],
+    },
+    CommandInfo {
+        glyph: 'Y',
+        name: "FixedPoint",
+        word: "fix",
+        effect: "a f -- b",
+        rule: ParseRule::ImplicitBlock(|| CatCommand::FixedPoint),
+        vectorization: Vectorization::None,
+        help: "Pops a function and executes it repeatedly until the top item of the stack no longer changes.",
+        details: &[],
+        examples: &[Example::new("100Y2/$", "0")],
+    },
+    CommandInfo {
+        glyph: '!',
+        name: "Execute",
+        word: "exec",
+        effect: "f --",
+        rule: ParseRule::Plain(|| CatCommand::Execute),
+        vectorization: Vectorization::None,
+        help: "Pops a function and executes it.",
+        details: &[],
+        examples: &[Example::new("2[3*]!", "6")],
+    },
+    CommandInfo {
+        glyph: 'S',
+        name: "Split",
+        word: "split",
+        effect: "s sep -- list",
+        rule: ParseRule::Plain(|| CatCommand::Split),
+        vectorization: Vectorization::Each,
+        help: "Pops a separator (string) and a string. Splits the string with the separator and collects the pieces to a list.",
+        details: &[],
+        examples: &[Example::new("\"a,b,c\"\",\"S", "(\"a\" \"b\" \"c\")")],
+    },
+    CommandInfo {
+        glyph: 'I',
+        name: "ToInteger",
+        word: "int",
+        effect: "a -- n",
+        rule: ParseRule::Plain(|| CatCommand::ToInteger),
+        vectorization: Vectorization::Each,
+        help: "Pops a value and coerces it to an integer. Strings are parsed and floats are truncated towards zero.",
+        details: &[],
+        examples: &[Example::new("\"42\"I", "42"), Example::new("¯2.7I", "¯2")],
+    },
+    CommandInfo {
+        glyph: 'r',
+        name: "Range",
+        word: "range",
+        effect: "n -- list",
+        rule: ParseRule::Plain(|| CatCommand::Range),
+        vectorization: Vectorization::Each,
+        help: "Pops a number n. Produces a list `(1..n)` (inclusive).",
+        details: &[],
+        examples: &[
+            Example::new("5r", "(1 2 3 4 5)"),
+            Example::new("(2 3)r", "((1 2) (1 2 3))"),
+        ],
+    },
+    CommandInfo {
+        glyph: ':',
+        name: "Duplicate",
+        word: "dup",
+        effect: "a -- a a",
+        rule: ParseRule::Plain(|| CatCommand::Duplicate),
+        vectorization: Vectorization::None,
+        help: "Duplicates the top element.",
+        details: &[],
+        examples: &[Example::new("1:", "1 1")],
+    },
+    CommandInfo {
+        glyph: ';',
+        name: "DuplicateSecond",
+        word: "dupd",
+        effect: "a b -- a a b",
+        rule: ParseRule::Plain(|| CatCommand::DuplicateSecond),
+        vectorization: Vectorization::None,
+        help: "Duplicates the second element, placing the result below the top item.",
+        details: &[],
+        examples: &[Example::new("2 3;", "2 2 3")],
+    },
+    CommandInfo {
+        glyph: '_',
+        name: "Drop",
+        word: "drop",
+        effect: "a --",
+        rule: ParseRule::Plain(|| CatCommand::Drop),
+        vectorization: Vectorization::None,
+        help: "Drops the top item.",
+        details: &[],
+        examples: &[Example::new("1 2_", "1")],
+    },
+    CommandInfo {
+        glyph: 'x',
+        name: "Rotate(2)",
+        word: "swap",
+        effect: "a b -- b a",
+        rule: ParseRule::Plain(|| CatCommand::Rotate(Rotation::Two)),
+        vectorization: Vectorization::None,
+        help: "Swaps the top 2 items.",
+        details: &[],
+        examples: &[Example::new("1 2x", "2 1")],
+    },
+    CommandInfo {
+        glyph: 'X',
+        name: "Rotate(3)",
+        word: "rot",
+        effect: "a b c -- c a b",
+        rule: ParseRule::Plain(|| CatCommand::Rotate(Rotation::Three)),
+        vectorization: Vectorization::None,
+        help: "Rotates the top 3 items.",
+        details: &[],
+        examples: &[Example::new("1 2 3X", "3 1 2")],
+    },
+    CommandInfo {
+        glyph: 'p',
+        name: "PushSide",
+        word: "push_side",
+        effect: "a -- a",
+        rule: ParseRule::Plain(|| CatCommand::PushSide),
+        vectorization: Vectorization::None,
+        help: "Pushes the top value to the side stack. Does not consume the value.",
+        details: &[],
+        examples: &[Example::new("1pP", "1 1")],
+    },
+    CommandInfo {
+        glyph: 'P',
+        name: "PopSide",
+        word: "pop_side",
+        effect: "-- a",
+        rule: ParseRule::Plain(|| CatCommand::PopSide),
+        vectorization: Vectorization::None,
+        help: "Pops the top item from the side stack.",
+        details: &[],
+        examples: &[Example::new("1p_P", "1")],
+    },
+    CommandInfo {
+        glyph: '~',
+        name: "ConsumeSide",
+        word: "take_side",
+        effect: "-- list",
+        rule: ParseRule::Plain(|| CatCommand::ConsumeSide),
+        vectorization: Vectorization::None,
+        help: "Consumes the entire side stack and pushes it as a list to the main stack.",
+        details: &[],
+        examples: &[Example::new("1p2p~", "1 2 (1 2)")],
+    },
+    CommandInfo {
+        glyph: 'J',
+        name: "Join",
+        word: "join",
+        effect: "list sep -- s",
+        rule: ParseRule::Plain(|| CatCommand::Join),
+        vectorization: Vectorization::None,
+        help: "Pops a separator and a list. Coerces each item of the list to strings and joins them using the separator. Joining a string joins its characters.",
+        details: &[],
+        examples: &[
+            Example::new("(1 2 3)\",\"J", "\"1,2,3\""),
+            Example::new("\"abc\"\"-\"J", "\"a-b-c\""),
+        ],
+    },
+    CommandInfo {
+        glyph: '=',
+        name: "Equal",
+        word: "eq",
+        effect: "a b -- bool",
+        rule: ParseRule::Plain(|| CatCommand::Equal),
+        vectorization: Vectorization::OneSide,
+        help: "Pops two values and pushes 1 if they are equal, 0 otherwise.",
+        details: &[],
+        examples: &[
+            Example::new("(1 2 3)2=", "(0 1 0)"),
+            Example::new("(1 2)(1 2)=", "1"),
+        ],
+    },
+    CommandInfo {
+        glyph: 'L',
+        name: "Less",
+        word: "lt",
+        effect: "a b -- bool",
+        rule: ParseRule::Plain(|| CatCommand::Less),
+        vectorization: Vectorization::OneSide,
+        help: "Pops `b` and `a` and pushes 1 if `a < b`, 0 otherwise.",
+        details: &[],
+        examples: &[Example::new("1 2L", "1")],
+    },
+    CommandInfo {
+        glyph: 'G',
+        name: "Greater",
+        word: "gt",
+        effect: "a b -- bool",
+        rule: ParseRule::Plain(|| CatCommand::Greater),
+        vectorization: Vectorization::OneSide,
+        help: "Pops `b` and `a` and pushes 1 if `a > b`, 0 otherwise.",
+        details: &[],
+        examples: &[Example::new("(1 2 3)2G", "(0 0 1)")],
+    },
+    CommandInfo {
+        glyph: 'N',
+        name: "Not",
+        word: "not",
+        effect: "a -- bool",
+        rule: ParseRule::Plain(|| CatCommand::Not),
+        vectorization: Vectorization::None,
+        help: "Pops a value and pushes 1 if it's false, 0 otherwise.",
+        details: &["Lists are tested as a whole, like in conditionals. `&N` negates each item instead."],
+        examples: &[
+            Example::new("0N", "1"),
+            Example::new("()N", "1"),
+            Example::new("(1 0 \"\")&N", "(0 1 1)"),
+        ],
+    },
+    CommandInfo {
+        glyph: 'A',
+        name: "And",
+        word: "and",
+        effect: "a b -- bool",
+        rule: ParseRule::Plain(|| CatCommand::And),
+        vectorization: Vectorization::None,
+        help: "Pops two values and pushes 1 if both are true, 0 otherwise.",
+        details: &["Lists are tested as a whole, like in conditionals."],
+        examples: &[Example::new("1 0A", "0"), Example::new("(0)1A", "1")],
+    },
+    CommandInfo {
+        glyph: 'O',
+        name: "Or",
+        word: "or",
+        effect: "a b -- bool",
+        rule: ParseRule::Plain(|| CatCommand::Or),
+        vectorization: Vectorization::None,
+        help: "Pops two values and pushes 1 if either is true, 0 otherwise.",
+        details: &["Lists are tested as a whole, like in conditionals."],
+        examples: &[Example::new("1 0O", "1"), Example::new("()0O", "0")],
+    },
+    CommandInfo {
+        glyph: '>',
+        name: "PushVariable",
+        word: "set",
+        effect: "a --",
+        rule: ParseRule::ReadsVariable(CatCommand::PushVariable),
+        vectorization: Vectorization::None,
+        help: "Pops the top item and writes it to the variable.",
+        details: &[],
+        examples: &[Example::new("10>x", "")],
+    },
+    CommandInfo {
+        glyph: '<',
+        name: "PopVariable",
+        word: "get",
+        effect: "-- a",
+        rule: ParseRule::ReadsVariable(|c| CatCommand::PopVariable(c, false)),
+        vectorization: Vectorization::None,
+        help: "Reads the value of the variable and pushes it to the stack. Does not consume the variable.",
+        details: &[],
+        examples: &[Example::new("10>x<x<x", "10 10")],
+    },
+];
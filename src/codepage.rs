@@ -0,0 +1,116 @@
+//! The single-byte code page used to score programs. Printable ASCII, tab
+//! and newline keep their ASCII bytes, and the remaining 159 bytes map to
+//! the high minus and other characters that are handy as variable names.
+
+use std::fmt;
+
+/// The character each byte stands for.
+#[rustfmt::skip]
+pub static CODE_PAGE: [char; 256] = [
+    '√', '∞', '∧', '∨', '∩', '∪', '≠', '≤', '≥', '\t', '\n', '≈', '≡', '⊂', '⊃', '⊆',
+    '⊇', '⌈', '⌉', '⌊', '⌋', '…', '•', '†', '‡', '‰', '‹', '›', '€', '™', '∘', '⊕',
+    ' ', '!', '"', '#', '$', '%', '&', '\'', '(', ')', '*', '+', ',', '-', '.', '/',
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':', ';', '<', '=', '>', '?',
+    '@', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+    'P', 'Q', 'R', 'S', 'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '[', '\\', ']', '^', '_',
+    '`', 'a', 'b', 'c', 'd', 'e', 'f', 'g', 'h', 'i', 'j', 'k', 'l', 'm', 'n', 'o',
+    'p', 'q', 'r', 's', 't', 'u', 'v', 'w', 'x', 'y', 'z', '{', '|', '}', '~', '⊗',
+    '¯', '¤', '¬', '±', '×', '÷', '·', '°', '¦', '§', '¶', '«', '»', '¿', '¡', '¢',
+    '£', '¥', '©', '®', 'µ', '¹', '²', '³', '¼', '½', '¾', 'α', 'β', 'γ', 'δ', 'ε',
+    'ζ', 'η', 'θ', 'ι', 'κ', 'λ', 'μ', 'ν', 'ξ', 'ο', 'π', 'ρ', 'σ', 'τ', 'υ', 'φ',
+    'χ', 'ψ', 'ω', 'Γ', 'Δ', 'Θ', 'Λ', 'Ξ', 'Π', 'Σ', 'Φ', 'Ψ', 'Ω', 'À', 'Á', 'Â',
+    'Ä', 'Å', 'Æ', 'Ç', 'È', 'É', 'Ê', 'Ë', 'Ì', 'Í', 'Î', 'Ï', 'Ñ', 'Ò', 'Ó', 'Ô',
+    'Ö', 'Ø', 'Ù', 'Ú', 'Û', 'Ü', 'Ý', 'ß', 'à', 'á', 'â', 'ä', 'å', 'æ', 'ç', 'è',
+    'é', 'ê', 'ë', 'ì', 'í', 'î', 'ï', 'ñ', 'ò', 'ó', 'ô', 'ö', 'ø', 'ù', 'ú', 'û',
+    'ü', 'ý', 'ÿ', '←', '↑', '→', '↓', '↔', '↕', '∀', '∃', '∅', '∈', '∉', '∑', '∏',
+];
+
+/// A character of the source that has no byte in the code page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EncodeError {
+    pub character: char,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} at {}:{} is not in the code page",
+            self.character, self.line, self.column
+        )
+    }
+}
+
+/// Finds the byte for a character.
+pub fn byte_of(c: char) -> Option<u8> {
+    CODE_PAGE.iter().position(|&p| p == c).map(|b| b as u8)
+}
+
+/// Encodes a program in the code page.
+pub fn encode(source: &str) -> Result<Vec<u8>, EncodeError> {
+    let mut line = 1;
+    let mut column = 1;
+    let mut bytes = Vec::with_capacity(source.len());
+    for c in source.chars() {
+        match byte_of(c) {
+            Some(b) => bytes.push(b),
+            None => {
+                return Err(EncodeError {
+                    character: c,
+                    line,
+                    column,
+                })
+            }
+        }
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    Ok(bytes)
+}
+
+/// Decodes a program stored in the code page. Every byte is valid.
+pub fn decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| CODE_PAGE[b as usize]).collect()
+}
+
+/// The score of a program: its length in the code page, or in UTF-8 if it
+/// uses characters outside the code page.
+pub fn score(source: &str) -> usize {
+    match encode(source) {
+        Ok(bytes) => bytes.len(),
+        Err(_) => source.len(),
+    }
+}
+
+/// Renders the code page as a Markdown table for spec.md, with a row for
+/// each high nibble and a column for each low nibble.
+pub fn table() -> String {
+    let mut out = String::from("|    |");
+    for low in 0..16 {
+        out.push_str(&format!(" _{:X} |", low));
+    }
+    out.push_str("\n|----|");
+    out.push_str(&"----|".repeat(16));
+    out.push('\n');
+    for (high, row) in CODE_PAGE.chunks(16).enumerate() {
+        out.push_str(&format!("| {:X}_ |", high));
+        for &c in row {
+            let cell = match c {
+                '\t' => "TAB".to_owned(),
+                '\n' => "LF".to_owned(),
+                ' ' => "SP".to_owned(),
+                c if c.is_ascii_punctuation() => format!("\\{}", c),
+                c => c.to_string(),
+            };
+            out.push_str(&format!(" {} |", cell));
+        }
+        out.push('\n');
+    }
+    out
+}
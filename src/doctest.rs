@@ -0,0 +1,160 @@
+//! Runs the katlang snippets in Markdown documents.
+//!
+//! A snippet is a fenced code block with the info string `katlang`. It can
+//! be followed by fenced blocks that describe it, separated only by blank
+//! lines:
+//!
+//! - `input`: fed to the program as stdin.
+//! - `stack`: the final stack, rendered like the examples in the command
+//!   reference, eg. `1 "a" (2 ¯3)`.
+//! - `output`: everything the program writes.
+//!
+//! A snippet without a `stack` or `output` block only has to run without
+//! errors.
+
+use builtins::render_stack;
+use std::time::Duration;
+use {run, Limits};
+
+/// A katlang snippet found in a Markdown document.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Snippet {
+    /// The line of the opening fence, starting from 1.
+    pub line: usize,
+    pub code: String,
+    pub input: String,
+    pub stack: Option<String>,
+    pub output: Option<String>,
+}
+
+/// Snippets are cut short if they don't finish in reasonable time.
+const SNIPPET_LIMITS: Limits = Limits {
+    max_steps: Some(1_000_000),
+    timeout: Some(Duration::from_secs(5)),
+    max_memory: Some(64 * 1024 * 1024),
+    max_depth: Some(1000),
+};
+
+impl Snippet {
+    /// Runs the snippet and compares the result with the expectations.
+    pub fn check(&self) -> Result<(), String> {
+        let result = run(&self.code, &self.input, &SNIPPET_LIMITS).map_err(|e| e.to_string())?;
+        let stack = render_stack(&result.stack);
+        if let Some(expected) = &self.stack {
+            if stack != *expected {
+                return Err(format!("expected the stack {}, got {}", expected, stack));
+            }
+        }
+        if let Some(expected) = &self.output {
+            if result.output != *expected {
+                return Err(format!(
+                    "expected the output {:?}, got {:?}",
+                    expected, result.output
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A fenced code block.
+struct Fence {
+    line: usize,
+    info: String,
+    text: String,
+}
+
+/// Finds the fenced code blocks of a document. Blank lines between blocks
+/// are skipped, and any other text is returned as `None` so that
+/// expectations can be tied to the snippet right before them.
+fn fences(markdown: &str) -> Result<Vec<Option<Fence>>, String> {
+    let mut blocks = Vec::new();
+    let mut lines = markdown.lines().enumerate();
+    while let Some((n, line)) = lines.next() {
+        let trimmed = line.trim_start();
+        if !trimmed.starts_with("```") {
+            if !trimmed.is_empty() {
+                blocks.push(None);
+            }
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let ticks = trimmed.chars().take_while(|&c| c == '`').count();
+        let info = trimmed[ticks..].trim().to_owned();
+        let mut text = String::new();
+        let mut closed = false;
+        for (_, line) in &mut lines {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with(&"`".repeat(ticks))
+                && trimmed.trim_end().chars().all(|c| c == '`')
+            {
+                closed = true;
+                break;
+            }
+            // Content is indented along with the fence in list items.
+            let skip = line.len() - line.trim_start_matches(' ').len();
+            text.push_str(&line[skip.min(indent)..]);
+            text.push('\n');
+        }
+        if !closed {
+            return Err(format!("line {}: code block is never closed", n + 1));
+        }
+        blocks.push(Some(Fence {
+            line: n + 1,
+            info,
+            text,
+        }));
+    }
+    Ok(blocks)
+}
+
+/// Collects the katlang snippets of a document along with their
+/// expectations.
+pub fn snippets(markdown: &str) -> Result<Vec<Snippet>, String> {
+    let mut snippets: Vec<Snippet> = Vec::new();
+    // Whether the last snippet can still take expectations.
+    let mut open = false;
+    for block in fences(markdown)? {
+        let fence = match block {
+            Some(fence) => fence,
+            None => {
+                open = false;
+                continue;
+            }
+        };
+        let mut text = fence.text;
+        match fence.info.as_str() {
+            "katlang" => {
+                // The newline before the closing fence isn't part of the code.
+                text.pop();
+                snippets.push(Snippet {
+                    line: fence.line,
+                    code: text,
+                    ..Snippet::default()
+                });
+                open = true;
+                continue;
+            }
+            "input" | "stack" | "output" => {}
+            _ => {
+                open = false;
+                continue;
+            }
+        }
+        let snippet = match snippets.last_mut() {
+            Some(snippet) if open => snippet,
+            _ => {
+                return Err(format!(
+                    "line {}: {} block doesn't follow a katlang snippet",
+                    fence.line, fence.info
+                ))
+            }
+        };
+        match fence.info.as_str() {
+            "input" => snippet.input = text,
+            "stack" => snippet.stack = Some(text.trim_end_matches('\n').to_owned()),
+            _ => snippet.output = Some(text),
+        }
+    }
+    Ok(snippets)
+}
@@ -0,0 +1,386 @@
+//! Turns parsed programs back into source code. The emitted source is
+//! canonical and as short as the emitter knows how to make it: single
+//! character strings use `'x`, blocks before commands that take an implicit
+//! block are written as the implicit block, `$` is left out where the block
+//! ends anyway and numbers are only separated by whitespace where needed. A
+//! number after a variable named `¯` is separated by an empty comment.
+//!
+//! Blocks hoisted from pre-named definitions are written back as pre-named
+//! definitions where that is shorter, or where their bodies use variables
+//! that aren't known yet at the start of the program.
+//!
+//! Parsing the emitted source gives back the same commands. Commands the
+//! terse parser can't produce, such as executing a variable named by a
+//! builtin glyph, are an error instead.
+
+use builtins::{self, ParseRule};
+use error::{EmitError, KatError};
+use parser::COMMENT;
+use spec::{CatCommand, Command, Span};
+
+/// What follows a sequence of commands in the emitted source.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum End {
+    /// The end of the program.
+    Eof,
+    /// A closing bracket.
+    Closer,
+    /// The `$` ending an implicit block.
+    Dollar,
+}
+
+/// A step of emitting a program.
+#[derive(Clone)]
+enum Task<'c> {
+    Sequence(&'c [Command], End),
+    Token(String),
+    Number(String),
+    /// The parser marks the variable as known.
+    Know(char),
+    /// Executes a variable, or writes a pre-named definition of it if it
+    /// isn't known yet. The flag tells if it's the last command before
+    /// `End`.
+    ExecuteVariable(char, Span, bool, End),
+    /// The hoisted definitions nested in a pre-named definition have been
+    /// written, and the definition itself is the one at this index. The
+    /// second index is the lowest one the enclosing definition allows.
+    Defined(usize, usize),
+}
+
+#[derive(Clone)]
+struct Emitter<'c> {
+    out: String,
+    /// Whether the last token was a number, which swallows a directly
+    /// following whitespace character.
+    after_number: bool,
+    /// Whether the last token was a variable named `¯`, which would make a
+    /// directly following number negative.
+    after_high_minus: bool,
+    /// The variables the parser knows at this point.
+    known: Vec<char>,
+    /// The definitions to write as pre-named blocks, in the order the
+    /// parser hoists them.
+    hoisted: &'c [Command],
+    /// How many of the hoisted definitions haven't been written yet. The
+    /// parser hoists each definition to the front when its body ends, so
+    /// they're written from the back.
+    remaining: usize,
+    /// The lowest index a definition can be picked from. Definitions nested
+    /// in another one are hoisted after it.
+    lowest: usize,
+    /// Whether executing an unknown variable is written as-is when it
+    /// doesn't match a hoisted definition.
+    lenient: bool,
+    /// The steps left, the next one last.
+    tasks: Vec<Task<'c>>,
+}
+
+/// Emits the source code of a program. Fails if the commands can't be
+/// written in terse source.
+pub fn emit(commands: &[Command]) -> Result<String, KatError> {
+    // Hoisted definitions are a block and a variable assignment each.
+    let definitions = commands
+        .chunks(2)
+        .take_while(|pair| match pair {
+            [block, assign] => matches!(
+                (&block.kind, &assign.kind),
+                (CatCommand::Block(_), CatCommand::PushVariable(_))
+            ),
+            _ => false,
+        })
+        .count();
+    // Each definition written as a pre-named block saves two characters.
+    for hoisted in (0..=definitions).rev() {
+        let emitter = Emitter::new(&commands[..hoisted * 2], &commands[hoisted * 2..], false);
+        if let Ok(out) = emitter.run() {
+            return Ok(out);
+        }
+    }
+    Emitter::new(&[], commands, true)
+        .run()
+        .map_err(|e| e.expect("Lenient emitting doesn't backtrack"))
+}
+
+/// Whether executing a variable can be written as its bare name. Other
+/// characters start literals or blocks, or are read as builtins.
+fn bare_variable(name: char) -> bool {
+    !(name.is_whitespace()
+        || name.is_ascii_digit()
+        || "\"'«¶[](){}$".contains(name)
+        || builtins::by_glyph(name).is_some())
+}
+
+impl<'c> Emitter<'c> {
+    fn new(hoisted: &'c [Command], commands: &'c [Command], lenient: bool) -> Emitter<'c> {
+        Emitter {
+            out: String::new(),
+            after_number: false,
+            after_high_minus: false,
+            known: vec![],
+            hoisted,
+            remaining: hoisted.len() / 2,
+            lowest: 0,
+            lenient,
+            tasks: vec![Task::Sequence(commands, End::Eof)],
+        }
+    }
+
+    /// Runs the tasks, backtracking when a pre-named definition turns out
+    /// to be the wrong one. Fails with `None` if no choice of definitions
+    /// works, and with an error if the commands can't be written at all.
+    fn run(mut self) -> Result<String, Option<KatError>> {
+        while let Some(task) = self.tasks.pop() {
+            match task {
+                Task::Sequence(commands, end) => self.sequence(commands, end),
+                Task::Token(text) => self.token(&text),
+                Task::Number(text) => {
+                    // Whitespace would push a string, so an empty comment
+                    // keeps `¯` from making the number negative.
+                    if self.after_high_minus && !text.starts_with('¯') {
+                        self.token(&format!("{}\n", COMMENT));
+                    }
+                    self.token(&text);
+                    self.after_number = true;
+                }
+                Task::Know(name) => {
+                    if !self.known.contains(&name) {
+                        self.known.push(name);
+                    }
+                }
+                Task::ExecuteVariable(name, span, last, end) => {
+                    if !bare_variable(name) {
+                        return self.fail(EmitError::UnwritableVariable(name), span);
+                    } else if self.known.contains(&name) {
+                        self.token(&name.to_string());
+                        self.after_high_minus = name == '¯';
+                    } else if self.lenient {
+                        // The name by itself would define the variable.
+                        return self.fail(EmitError::UndefinedVariable(name), span);
+                    } else {
+                        return self.define(name, last, end);
+                    }
+                }
+                Task::Defined(index, lowest) => {
+                    if self.remaining != index + 1 {
+                        return Err(None);
+                    }
+                    self.remaining = index;
+                    self.lowest = lowest;
+                }
+            }
+        }
+        if self.remaining == 0 {
+            Ok(self.out)
+        } else {
+            Err(None)
+        }
+    }
+
+    /// Fails with an error when emitting leniently, and backtracks
+    /// otherwise, since the lenient emitter runs last and reports it.
+    fn fail(&self, error: EmitError, span: Span) -> Result<String, Option<KatError>> {
+        if self.lenient {
+            Err(Some(KatError::Emit(error, span)))
+        } else {
+            Err(None)
+        }
+    }
+
+    /// Tries each hoisted definition of `name` that hasn't been written.
+    /// Definitions nested in its body are hoisted before it, so it must be
+    /// the last one its body leaves unwritten.
+    fn define(self, name: char, last: bool, end: End) -> Result<String, Option<KatError>> {
+        for index in (self.lowest..self.remaining).rev() {
+            let body = match (
+                &self.hoisted[index * 2].kind,
+                &self.hoisted[index * 2 + 1].kind,
+            ) {
+                (CatCommand::Block(body), CatCommand::PushVariable(c)) if *c == name => body,
+                _ => continue,
+            };
+            // The closing brace can be left out at the end of the program.
+            let open = last && end == End::Eof;
+            let mut attempt = self.clone();
+            attempt.token(&name.to_string());
+            attempt.after_high_minus = name == '¯';
+            attempt.lowest = index + 1;
+            attempt.push(vec![
+                Task::Sequence(body, if open { End::Eof } else { End::Closer }),
+                Task::Defined(index, self.lowest),
+                Task::Token(if open { "" } else { "}" }.to_owned()),
+                Task::Know(name),
+            ]);
+            if let Ok(out) = attempt.run() {
+                return Ok(out);
+            }
+        }
+        Err(None)
+    }
+
+    /// Schedules tasks to run in order, before the ones already scheduled.
+    fn push(&mut self, tasks: Vec<Task<'c>>) {
+        self.tasks.extend(tasks.into_iter().rev());
+    }
+
+    fn token(&mut self, text: &str) {
+        let first = match text.chars().next() {
+            Some(c) => c,
+            None => return,
+        };
+        self.after_high_minus = false;
+        if self.after_number && (first.is_ascii_digit() || first == '.' || first.is_whitespace()) {
+            self.out.push(' ');
+        }
+        // The parser consumes closing brackets, and the name after `{...}`,
+        // without resetting the whitespace a number swallows.
+        if !matches!(first, ')' | ']' | '}') {
+            self.after_number = false;
+        }
+        self.out.push_str(text);
+    }
+
+    /// Schedules the first command of a sequence, along with a block parsed
+    /// before it, and then the rest of the sequence.
+    fn sequence(&mut self, commands: &'c [Command], end: End) {
+        let (command, rest) = match commands.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+        let last = rest.len() == 1;
+        let absorbed = match (&command.kind, rest.first().map(|c| &c.kind)) {
+            (CatCommand::Block(body), Some(CatCommand::PushVariable(name))) => Some(vec![
+                Task::Token("{".to_owned()),
+                Task::Sequence(body, End::Closer),
+                Task::Token(format!("}}{}", name)),
+                Task::Know(*name),
+            ]),
+            // Quoting a command also reads its implicit block.
+            (CatCommand::Block(body), Some(CatCommand::CreateCommand(inner))) => {
+                implicit_glyph(&inner.kind).map(|glyph| {
+                    let mut tasks = vec![Task::Token("`".to_owned())];
+                    tasks.extend(implicit(glyph, Some(body), last, end));
+                    tasks
+                })
+            }
+            (CatCommand::Block(body), Some(next)) => {
+                implicit_glyph(next).map(|glyph| implicit(glyph, Some(body), last, end))
+            }
+            _ => None,
+        };
+        match absorbed {
+            Some(tasks) => {
+                self.tasks.push(Task::Sequence(&rest[1..], end));
+                self.push(tasks);
+            }
+            None => {
+                self.tasks.push(Task::Sequence(rest, end));
+                self.push(command_tasks(command, rest.is_empty(), end));
+            }
+        }
+    }
+}
+
+/// The tasks for a single command.
+fn command_tasks(command: &Command, last: bool, end: End) -> Vec<Task<'_>> {
+    match &command.kind {
+        CatCommand::Block(body) => vec![
+            Task::Token("[".to_owned()),
+            Task::Sequence(body, End::Closer),
+            Task::Token("]".to_owned()),
+        ],
+        CatCommand::List(body) => vec![
+            Task::Token("(".to_owned()),
+            Task::Sequence(body, End::Closer),
+            Task::Token(")".to_owned()),
+        ],
+        CatCommand::CreateInteger(v) => vec![Task::Number(v.to_string().replace('-', "¯"))],
+        CatCommand::CreateFloat(v) => {
+            let mut text = v.to_string().replace('-', "¯");
+            if !text.contains('.') {
+                text.push_str(".0");
+            }
+            vec![Task::Number(text)]
+        }
+        CatCommand::CreateString(v) => vec![Task::Token(string(v))],
+        CatCommand::CreateCommand(inner) => {
+            let mut tasks = vec![Task::Token("`".to_owned())];
+            tasks.extend(command_tasks(inner, last, end));
+            tasks
+        }
+        CatCommand::PushVariable(c) => vec![Task::Token(format!(">{}", c)), Task::Know(*c)],
+        CatCommand::PopVariable(c, false) => vec![Task::Token(format!("<{}", c))],
+        CatCommand::PopVariable(c, true) => {
+            vec![Task::ExecuteVariable(*c, command.span, last, end)]
+        }
+        CatCommand::Native(native) => vec![Task::Token(native.name().to_owned())],
+        kind => match implicit_glyph(kind) {
+            Some(glyph) => implicit(glyph, None, last, end),
+            None => {
+                let info = builtins::info(kind).expect("Builtin command missing from BUILTINS");
+                vec![Task::Token(info.glyph.to_string())]
+            }
+        },
+    }
+}
+
+/// The tasks for a command that takes an implicit block, along with the
+/// block parsed before it.
+fn implicit(glyph: char, body: Option<&[Command]>, last: bool, end: End) -> Vec<Task<'_>> {
+    match body {
+        // An empty implicit block can only be written at the end.
+        Some(body) if !body.is_empty() || (last && end == End::Eof) => {
+            if last && end != End::Dollar {
+                vec![Task::Token(glyph.to_string()), Task::Sequence(body, end)]
+            } else {
+                vec![
+                    Task::Token(glyph.to_string()),
+                    Task::Sequence(body, End::Dollar),
+                    Task::Token("$".to_owned()),
+                ]
+            }
+        }
+        Some(_) => {
+            let mut tasks = vec![Task::Token("[]".to_owned())];
+            tasks.extend(implicit(glyph, None, last, end));
+            tasks
+        }
+        None => {
+            if last && end == End::Closer {
+                vec![Task::Token(glyph.to_string())]
+            } else {
+                vec![Task::Token(format!("{}$", glyph))]
+            }
+        }
+    }
+}
+
+/// The glyph of a command that takes an implicit block.
+fn implicit_glyph(command: &CatCommand) -> Option<char> {
+    builtins::info(command).and_then(|info| match info.rule {
+        ParseRule::ImplicitBlock(_) => Some(info.glyph),
+        _ => None,
+    })
+}
+
+/// The shortest literal for a string. A whitespace character is a literal
+/// by itself, and a raw string saves the backslashes of a string with
+/// quotes or backslashes in it.
+fn string(v: &str) -> String {
+    let mut chars = v.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_whitespace() => c.to_string(),
+        (Some(c), None) => format!("'{}", c),
+        _ if v.contains(['"', '\\']) && !v.contains('»') => format!("«{}»", v),
+        _ => {
+            let mut out = String::from("\"");
+            for c in v.chars() {
+                if c == '"' || c == '\\' {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push('"');
+            out
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use spec::Span;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParseError {
+    UnexpectedCharacter(char),
+    UnexpectedEof,
+    UnclosedBlock(char),
+    UnmatchedClose(char),
+    MismatchedClose(char, char),
+    UnknownWord(String),
+    UnclosedString(char),
+    InvalidEscape(String),
+    TooDeeplyNested(usize),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum RuntimeError {
+    EmptyStack,
+    EmptySideStack,
+    EmptyVariable(char),
+    NotAnInteger(String),
+    NotANumber(String),
+    NotExecutable(String),
+    Overflow(&'static str),
+    DivisionByZero,
+    EmptyFold,
+    EndOfInput,
+    Io(String),
+    StepLimitExceeded(u64),
+    Timeout,
+    MemoryLimitExceeded(usize),
+    DepthLimitExceeded(usize),
+    WrongType {
+        command: &'static str,
+        expected: &'static str,
+        found: String,
+    },
+}
+
+/// Commands that can't be written back as terse source.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EmitError {
+    /// The variable is executed, but its name starts a literal or is read
+    /// as a builtin.
+    UnwritableVariable(char),
+    /// The variable is executed before anything assigns it, where its name
+    /// would define it instead.
+    UndefinedVariable(char),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KatError {
+    Parse(ParseError, Span),
+    Runtime(RuntimeError, Option<Span>),
+    Emit(EmitError, Span),
+}
+
+impl KatError {
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            KatError::Parse(_, span) | KatError::Emit(_, span) => Some(*span),
+            KatError::Runtime(_, span) => *span,
+        }
+    }
+
+    /// Attaches a span to a runtime error that doesn't have one yet.
+    /// Errors raised deeper in the call chain keep their original location.
+    pub fn or_span(self, span: Span) -> KatError {
+        match self {
+            KatError::Runtime(e, None) => KatError::Runtime(e, Some(span)),
+            e => e,
+        }
+    }
+
+    /// Renders the error message followed by the offending source line and
+    /// a caret pointing at the span.
+    pub fn render(&self, source: &str) -> String {
+        let span = match self.span() {
+            Some(span) => span,
+            None => return self.to_string(),
+        };
+        let line = source.split('\n').nth(span.line - 1).unwrap_or("");
+        let rest = line.chars().count().saturating_sub(span.column - 1);
+        format!(
+            "{}\n{}\n{}{}",
+            self,
+            line,
+            " ".repeat(span.column - 1),
+            "^".repeat(span.len.min(rest).max(1))
+        )
+    }
+}
+
+impl From<RuntimeError> for KatError {
+    fn from(e: RuntimeError) -> KatError {
+        KatError::Runtime(e, None)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedCharacter(c) => write!(f, "Unexpected character: {}", c),
+            ParseError::UnexpectedEof => write!(f, "Unexpected EOF"),
+            ParseError::UnclosedBlock(c) => write!(f, "Block is never closed, expected {}", c),
+            ParseError::UnmatchedClose(c) => write!(f, "Unmatched closing bracket: {}", c),
+            ParseError::MismatchedClose(expected, found) => {
+                write!(
+                    f,
+                    "Expected {} to close the block, found {}",
+                    expected, found
+                )
+            }
+            ParseError::UnknownWord(word) => write!(f, "Unknown word: {}", word),
+            ParseError::UnclosedString(c) => write!(f, "String is never closed, expected {}", c),
+            ParseError::InvalidEscape(escape) => write!(f, "Invalid escape sequence: {}", escape),
+            ParseError::TooDeeplyNested(max) => {
+                write!(f, "Blocks are nested more than {} deep", max)
+            }
+        }
+    }
+}
+
+impl fmt::Display for EmitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EmitError::UnwritableVariable(c) => write!(
+                f,
+                "The variable {} can't be executed by name in terse source",
+                c
+            ),
+            EmitError::UndefinedVariable(c) => write!(
+                f,
+                "The variable {} is executed before it's assigned, which terse source can't express",
+                c
+            ),
+        }
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuntimeError::EmptyStack => write!(f, "Pop from an empty stack"),
+            RuntimeError::EmptySideStack => write!(f, "Pop from an empty side stack"),
+            RuntimeError::EmptyVariable(c) => write!(f, "Fetched from an empty variable: {}", c),
+            RuntimeError::NotAnInteger(v) => {
+                write!(f, "String doesn't represent an integer: {}", v)
+            }
+            RuntimeError::NotANumber(v) => write!(f, "String doesn't represent a number: {}", v),
+            RuntimeError::NotExecutable(v) => write!(f, "Can't execute {}", v),
+            RuntimeError::Overflow(command) => write!(f, "{} overflowed", command),
+            RuntimeError::DivisionByZero => write!(f, "Division by zero"),
+            RuntimeError::EmptyFold => write!(f, "Fold over an empty list"),
+            RuntimeError::EndOfInput => write!(f, "Read past the end of input"),
+            RuntimeError::Io(e) => write!(f, "I/O error: {}", e),
+            RuntimeError::StepLimitExceeded(steps) => {
+                write!(f, "Step limit of {} commands exceeded", steps)
+            }
+            RuntimeError::Timeout => write!(f, "Execution timed out"),
+            RuntimeError::MemoryLimitExceeded(bytes) => {
+                write!(f, "Memory limit of {} bytes exceeded", bytes)
+            }
+            RuntimeError::DepthLimitExceeded(depth) => {
+                write!(f, "Recursion depth limit of {} exceeded", depth)
+            }
+            RuntimeError::WrongType {
+                command,
+                expected,
+                found,
+            } => write!(f, "{} expected {}, found {}", command, expected, found),
+        }
+    }
+}
+
+impl fmt::Display for KatError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KatError::Parse(e, span) => {
+                write!(f, "Parse error at {}:{}: {}", span.line, span.column, e)
+            }
+            KatError::Runtime(e, Some(span)) => {
+                write!(f, "Runtime error at {}:{}: {}", span.line, span.column, e)
+            }
+            KatError::Runtime(e, None) => write!(f, "Runtime error: {}", e),
+            KatError::Emit(e, span) => {
+                write!(f, "Emit error at {}:{}: {}", span.line, span.column, e)
+            }
+        }
+    }
+}
@@ -0,0 +1,178 @@
+//! Lays out a parsed program the way golf answers are usually explained:
+//! one command per line, placed under its column in the source, with its
+//! name and stack effect. The commands of a block are indented under it.
+//!
+//! ```
+//! use katlang::explain::explain;
+//!
+//! let code = "(1 2)&1+";
+//! let commands = katlang::parse(code).unwrap();
+//! assert_eq!(
+//!     explain(code, &commands),
+//!     "\
+//! (1 2)&1+
+//! (         List      -- list
+//!  1          Push 1  -- n
+//!    2        Push 2  -- n
+//!     )
+//!      &    Map       list f -- list
+//!       1     Push 1  -- n
+//!        +    Add     a b -- c
+//! "
+//! );
+//! ```
+
+use builtins::{self, ParseRule};
+use spec::{CatCommand, Command, Span};
+
+/// One line of the explanation.
+struct Line {
+    column: usize,
+    text: String,
+    depth: usize,
+    name: String,
+    effect: &'static str,
+}
+
+/// Explains a program. `commands` must have been parsed from `source`.
+pub fn explain(source: &str, commands: &[Command]) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut lines = Vec::new();
+    explain_block(&chars, commands, 0, &mut lines);
+
+    let code_width = lines
+        .iter()
+        .map(|line| line.column + line.text.chars().count())
+        .max()
+        .unwrap_or(0);
+    let name_width = lines
+        .iter()
+        .map(|line| line.depth * 2 + line.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for line in source.lines() {
+        out.push_str(line);
+        out.push('\n');
+    }
+    for line in lines {
+        let code = format!("{}{}", " ".repeat(line.column), line.text);
+        let name = format!("{}{}", "  ".repeat(line.depth), line.name);
+        let text = format!(
+            "{:<cw$}  {:<nw$}  {}",
+            code,
+            name,
+            line.effect,
+            cw = code_width,
+            nw = name_width
+        );
+        out.push_str(text.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn explain_block(chars: &[char], commands: &[Command], depth: usize, lines: &mut Vec<Line>) {
+    // Pair commands with the implicit blocks parsed before them. A command's
+    // implicit block starts at the command's glyph.
+    let mut nodes: Vec<(&Command, Option<&[Command]>)> = Vec::new();
+    let mut i = 0;
+    while i < commands.len() {
+        let command = &commands[i];
+        if let (CatCommand::Block(body), Some(next)) = (&command.kind, commands.get(i + 1)) {
+            let implicit = builtins::info(&next.kind)
+                .is_some_and(|info| matches!(info.rule, ParseRule::ImplicitBlock(_)));
+            if implicit && next.span.offset == command.span.offset {
+                nodes.push((next, Some(body)));
+                i += 2;
+                continue;
+            }
+        }
+        nodes.push((command, None));
+        i += 1;
+    }
+    // Hoisted variable definitions are moved back to where they're written.
+    nodes.sort_by_key(|(command, _)| command.span.offset);
+
+    for (command, implicit) in nodes {
+        match (&command.kind, implicit) {
+            (_, Some(body)) => {
+                lines.push(line(chars, command.span, 1, depth, command));
+                explain_block(chars, body, depth + 1, lines);
+            }
+            (CatCommand::Block(body), None) | (CatCommand::List(body), None) => {
+                let bracketed = ['[', '(', '{'].contains(&chars[command.span.offset]);
+                lines.push(line(chars, command.span, 1, depth, command));
+                explain_block(chars, body, depth + 1, lines);
+                if bracketed {
+                    let end = command.span.offset + command.span.len - 1;
+                    lines.push(Line {
+                        column: column_of(chars, end),
+                        text: chars[end].to_string(),
+                        depth,
+                        name: String::new(),
+                        effect: "",
+                    });
+                }
+            }
+            _ => lines.push(line(chars, command.span, command.span.len, depth, command)),
+        }
+    }
+}
+
+/// Describes a command, showing the first `len` characters of its span.
+fn line(chars: &[char], span: Span, len: usize, depth: usize, command: &Command) -> Line {
+    // Only the first line of a multi-line span is shown, and a newline
+    // literal is shown as a symbol.
+    let text = if chars[span.offset] == '\n' {
+        "␤".to_owned()
+    } else {
+        chars[span.offset..span.offset + len]
+            .iter()
+            .take_while(|&&c| c != '\n')
+            .collect()
+    };
+    let (name, effect) = describe(&command.kind);
+    Line {
+        column: span.column - 1,
+        text,
+        depth,
+        name,
+        effect,
+    }
+}
+
+/// The column of a character offset, starting from 0.
+fn column_of(chars: &[char], offset: usize) -> usize {
+    chars[..offset]
+        .iter()
+        .rev()
+        .take_while(|&&c| c != '\n')
+        .count()
+}
+
+/// The human name and the stack effect of a command.
+fn describe(command: &CatCommand) -> (String, &'static str) {
+    if let Some(info) = builtins::info(command) {
+        return match command {
+            CatCommand::PushVariable(c) | CatCommand::PopVariable(c, _) => {
+                (format!("{} {}", info.name, c), info.effect)
+            }
+            CatCommand::CreateCommand(inner) => (
+                format!("{} {}", info.name, describe(&inner.kind).0),
+                info.effect,
+            ),
+            _ => (info.name.to_owned(), info.effect),
+        };
+    }
+    match command {
+        CatCommand::Block(_) => ("Block".to_owned(), "-- f"),
+        CatCommand::List(_) => ("List".to_owned(), "-- list"),
+        CatCommand::CreateInteger(v) => (format!("Push {}", v).replace('-', "¯"), "-- n"),
+        CatCommand::CreateFloat(v) => (format!("Push {:?}", v).replace('-', "¯"), "-- x"),
+        CatCommand::CreateString(v) => (format!("Push {:?}", v), "-- s"),
+        CatCommand::Native(v) => (format!("Native {}", v.name()), ""),
+        CatCommand::PopVariable(c, _) => (format!("ExecuteVariable {}", c), ""),
+        _ => unreachable!("Builtin command missing from BUILTINS"),
+    }
+}
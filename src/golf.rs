@@ -0,0 +1,242 @@
+//! Shrinks programs with rewrites that keep their behaviour:
+//!
+//! - The program is emitted in its shortest form, which among other things
+//!   writes `[...]&` as the implicit block `&...`.
+//! - A literal repeated right after itself, like `1 1`, becomes `1:`.
+//! - A block written more than once is defined once as `{...}v` and pushed
+//!   with `<v` where it's used.
+//! - A `W` at the end of the program is dropped, since the top of the stack
+//!   is printed anyway.
+//!
+//! The rewrites are greedy: each one is kept if it makes the program shorter
+//! in the code page and the result still passes the test cases.
+
+use builtins;
+use codepage::score;
+use emit::emit;
+use spec::{CatCommand, Command, Span};
+use {parse, KatError};
+
+/// A golfed program and the rewrites that got there.
+#[derive(Clone, Debug)]
+pub struct Golfed {
+    pub code: String,
+    /// Describes each rewrite applied, in order.
+    pub rewrites: Vec<String>,
+}
+
+/// Golfs a program. `passes` tells if a rewritten program still does its
+/// job, usually by running it on test cases.
+pub fn golf<F: Fn(&str) -> bool>(code: &str, passes: F) -> Result<Golfed, KatError> {
+    let commands = parse(code)?;
+    let mut best = Golfed {
+        code: code.to_owned(),
+        rewrites: vec![],
+    };
+    let emitted = emit(&commands)?;
+    if score(&emitted) < score(code) && passes(&emitted) {
+        best.code = emitted;
+        best.rewrites
+            .push("Emitted in the shortest form".to_owned());
+    }
+    Ok(shrink(best, commands, passes))
+}
+
+/// Golfs parsed commands, such as a program read from verbose source. The
+/// golfed program is terse source, starting from the emitted commands.
+pub fn golf_commands<F: Fn(&str) -> bool>(
+    commands: Vec<Command>,
+    passes: F,
+) -> Result<Golfed, KatError> {
+    let best = Golfed {
+        code: emit(&commands)?,
+        rewrites: vec!["Emitted as terse source".to_owned()],
+    };
+    Ok(shrink(best, commands, passes))
+}
+
+/// Applies rewrites to the commands of `best` while they keep shrinking it.
+fn shrink<F: Fn(&str) -> bool>(mut best: Golfed, mut commands: Vec<Command>, passes: F) -> Golfed {
+    'search: loop {
+        for (rewrite, candidate) in candidates(&commands) {
+            let code = match emit(&candidate) {
+                Ok(code) => code,
+                Err(_) => continue,
+            };
+            if score(&code) < score(&best.code) && passes(&code) {
+                commands = candidate;
+                best.code = code;
+                best.rewrites.push(rewrite);
+                continue 'search;
+            }
+        }
+        return best;
+    }
+}
+
+/// Every single rewrite of a program, with a description of each.
+fn candidates(commands: &[Command]) -> Vec<(String, Vec<Command>)> {
+    let mut out = Vec::new();
+    for candidate in in_sequences(commands, &duplicate_literals) {
+        out.push(("Duplicated a repeated literal".to_owned(), candidate));
+    }
+    if let Some(name) = free_variable(commands) {
+        for body in repeated_blocks(commands) {
+            out.push((
+                format!("Moved a repeated block to the variable {}", name),
+                hoist_block(commands, &body, name),
+            ));
+        }
+    }
+    if let Some((last, rest)) = commands.split_last() {
+        if last.kind == CatCommand::WriteLine {
+            out.push(("Dropped the final W".to_owned(), rest.to_vec()));
+        }
+    }
+    out
+}
+
+/// Applies `rewrite` to the program and to each block and list in it. Each
+/// sequence the rewrite returns gives a rewritten program.
+fn in_sequences(
+    commands: &[Command],
+    rewrite: &dyn Fn(&[Command]) -> Vec<Vec<Command>>,
+) -> Vec<Vec<Command>> {
+    let mut out = rewrite(commands);
+    for (i, command) in commands.iter().enumerate() {
+        let body = match &command.kind {
+            CatCommand::Block(body) | CatCommand::List(body) => body,
+            _ => continue,
+        };
+        for body in in_sequences(body, rewrite) {
+            let kind = match command.kind {
+                CatCommand::Block(_) => CatCommand::Block(body),
+                _ => CatCommand::List(body),
+            };
+            let mut candidate = commands.to_vec();
+            candidate[i] = Command::new(kind, command.span);
+            out.push(candidate);
+        }
+    }
+    out
+}
+
+/// Replaces a literal right after an equal one with `:`.
+fn duplicate_literals(commands: &[Command]) -> Vec<Vec<Command>> {
+    let mut out = Vec::new();
+    for i in 1..commands.len() {
+        let literal = matches!(
+            commands[i].kind,
+            CatCommand::CreateInteger(_)
+                | CatCommand::CreateFloat(_)
+                | CatCommand::CreateString(_)
+                | CatCommand::Block(_)
+        );
+        if literal && commands[i].kind == commands[i - 1].kind {
+            let mut candidate = commands.to_vec();
+            candidate[i] = Command::new(CatCommand::Duplicate, commands[i].span);
+            out.push(candidate);
+        }
+    }
+    out
+}
+
+/// Calls `f` with each block that pushes its value, and doesn't define a
+/// variable.
+fn visit_blocks<'c>(commands: &'c [Command], f: &mut dyn FnMut(&'c [Command])) {
+    for (i, command) in commands.iter().enumerate() {
+        let defines = matches!(
+            commands.get(i + 1).map(|c| &c.kind),
+            Some(CatCommand::PushVariable(_))
+        );
+        match &command.kind {
+            CatCommand::Block(body) if !defines => {
+                f(body);
+                visit_blocks(body, f);
+            }
+            CatCommand::Block(body) | CatCommand::List(body) => visit_blocks(body, f),
+            _ => {}
+        }
+    }
+}
+
+/// The bodies of the blocks written more than once.
+fn repeated_blocks(commands: &[Command]) -> Vec<Vec<Command>> {
+    let mut counts: Vec<(&[Command], usize)> = Vec::new();
+    visit_blocks(
+        commands,
+        &mut |body| match counts.iter_mut().find(|(b, _)| *b == body) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((body, 1)),
+        },
+    );
+    counts
+        .into_iter()
+        .filter(|&(body, count)| count > 1 && !body.is_empty())
+        .map(|(body, _)| body.to_vec())
+        .collect()
+}
+
+/// Assigns a block to the variable `name` at the start of the program and
+/// pushes the variable wherever the block was written.
+fn hoist_block(commands: &[Command], body: &[Command], name: char) -> Vec<Command> {
+    fn replace(commands: &[Command], body: &[Command], name: char) -> Vec<Command> {
+        let mut out = Vec::new();
+        for (i, command) in commands.iter().enumerate() {
+            let defines = matches!(
+                commands.get(i + 1).map(|c| &c.kind),
+                Some(CatCommand::PushVariable(_))
+            );
+            let kind = match &command.kind {
+                CatCommand::Block(b) if !defines && b == body => {
+                    CatCommand::PopVariable(name, false)
+                }
+                CatCommand::Block(b) => CatCommand::Block(replace(b, body, name)),
+                CatCommand::List(b) => CatCommand::List(replace(b, body, name)),
+                kind => kind.clone(),
+            };
+            out.push(Command::new(kind, command.span));
+        }
+        out
+    }
+
+    let mut out = replace(commands, body, name);
+    // Definitions hoisted by the parser stay in front, so that the emitter
+    // can still write them as pre-named blocks.
+    let hoisted = out
+        .chunks(2)
+        .take_while(|pair| match pair {
+            [block, assign] => matches!(
+                (&block.kind, &assign.kind),
+                (CatCommand::Block(_), CatCommand::PushVariable(_))
+            ),
+            _ => false,
+        })
+        .count();
+    let definition = vec![
+        Command::new(CatCommand::Block(body.to_vec()), Span::default()),
+        Command::new(CatCommand::PushVariable(name), Span::default()),
+    ];
+    out.splice(hoisted * 2..hoisted * 2, definition);
+    out
+}
+
+/// A letter that isn't a builtin and isn't used as a variable anywhere in
+/// the program.
+fn free_variable(commands: &[Command]) -> Option<char> {
+    fn uses(commands: &[Command], name: char) -> bool {
+        commands.iter().any(|command| uses_in(&command.kind, name))
+    }
+    fn uses_in(command: &CatCommand, name: char) -> bool {
+        match command {
+            CatCommand::PushVariable(c) | CatCommand::PopVariable(c, _) => *c == name,
+            CatCommand::Block(body) | CatCommand::List(body) => uses(body, name),
+            CatCommand::CreateCommand(inner) => uses_in(&inner.kind, name),
+            _ => false,
+        }
+    }
+
+    ('a'..='z')
+        .chain('A'..='Z')
+        .find(|&c| builtins::by_glyph(c).is_none() && !uses(commands, c))
+}
@@ -0,0 +1,256 @@
+use num_bigint::BigInt;
+use num_integer::Integer as NumInteger;
+use num_traits::{FromPrimitive, Pow, Signed, ToPrimitive};
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// An arbitrary-precision integer. Values that fit in an `i64` are always
+/// stored as `Small`, so the common case never allocates and the derived
+/// equality is correct.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum Integer {
+    Small(i64),
+    Big(BigInt),
+}
+use self::Integer::*;
+
+impl Integer {
+    fn from_big(value: BigInt) -> Integer {
+        match value.to_i64() {
+            Some(v) => Small(v),
+            None => Big(value),
+        }
+    }
+
+    fn to_big(&self) -> BigInt {
+        match self {
+            Small(v) => BigInt::from(*v),
+            Big(v) => v.clone(),
+        }
+    }
+
+    /// The slow path of binary operations, kept out of line so the small
+    /// integer fast paths stay cheap to inline.
+    #[cold]
+    #[inline(never)]
+    fn big_op(&self, other: &Integer, func: impl FnOnce(BigInt, BigInt) -> BigInt) -> Integer {
+        Integer::from_big(func(self.to_big(), other.to_big()))
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        match self {
+            Small(v) => Some(*v),
+            Big(_) => None,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            Small(v) => *v as f64,
+            Big(v) => v.to_f64().unwrap_or(f64::NAN),
+        }
+    }
+
+    /// Truncates a float towards zero. Returns `None` for NaN and infinities.
+    pub fn from_f64(value: f64) -> Option<Integer> {
+        if !value.is_finite() {
+            return None;
+        }
+        let value = value.trunc();
+        if value.abs() < i64::MAX as f64 {
+            Some(Small(value as i64))
+        } else {
+            BigInt::from_f64(value).map(Integer::from_big)
+        }
+    }
+
+    /// Compares with a float exactly, without rounding either side. NaN is
+    /// greater than every integer.
+    pub fn cmp_f64(&self, other: f64) -> Ordering {
+        if other.is_nan() {
+            return Ordering::Less;
+        }
+        if other.is_infinite() {
+            return if other > 0.0 {
+                Ordering::Less
+            } else {
+                Ordering::Greater
+            };
+        }
+        // The integer part of a finite float converts exactly, so only the
+        // fraction is left to break a tie.
+        let whole = Integer::from_f64(other).unwrap();
+        self.cmp(&whole)
+            .then_with(|| 0.0.partial_cmp(&other.fract()).unwrap())
+    }
+
+    /// Converts to an `i64`, clamping values that are out of range.
+    pub fn clamp_to_i64(&self) -> i64 {
+        match self {
+            Small(v) => *v,
+            Big(v) if v.is_negative() => i64::MIN,
+            Big(_) => i64::MAX,
+        }
+    }
+
+    /// The number of bits needed to store the magnitude.
+    pub fn bits(&self) -> u64 {
+        match self {
+            Small(v) => 64 - v.unsigned_abs().leading_zeros() as u64,
+            Big(v) => v.bits(),
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        match self {
+            Small(v) => *v == 0,
+            Big(_) => false,
+        }
+    }
+
+    #[inline]
+    pub fn add(&self, other: &Integer) -> Integer {
+        if let (Small(a), Small(b)) = (self, other) {
+            if let Some(v) = a.checked_add(*b) {
+                return Small(v);
+            }
+        }
+        self.big_op(other, |a, b| a + b)
+    }
+
+    #[inline]
+    pub fn sub(&self, other: &Integer) -> Integer {
+        if let (Small(a), Small(b)) = (self, other) {
+            if let Some(v) = a.checked_sub(*b) {
+                return Small(v);
+            }
+        }
+        self.big_op(other, |a, b| a - b)
+    }
+
+    #[inline]
+    pub fn mul(&self, other: &Integer) -> Integer {
+        if let (Small(a), Small(b)) = (self, other) {
+            if let Some(v) = a.checked_mul(*b) {
+                return Small(v);
+            }
+        }
+        self.big_op(other, |a, b| a * b)
+    }
+
+    /// Division rounding towards negative infinity. Returns `None` when
+    /// dividing by zero.
+    #[inline]
+    pub fn floor_div(&self, other: &Integer) -> Option<Integer> {
+        if other.is_zero() {
+            return None;
+        }
+        if let (Small(a), Small(b)) = (self, other) {
+            if *a != i64::MIN || *b != -1 {
+                return Some(Small(a.div_floor(b)));
+            }
+        }
+        Some(self.big_op(other, |a, b| a.div_floor(&b)))
+    }
+
+    /// Remainder with the sign of the divisor, matching `floor_div`. Returns
+    /// `None` when dividing by zero.
+    #[inline]
+    pub fn floor_mod(&self, other: &Integer) -> Option<Integer> {
+        if other.is_zero() {
+            return None;
+        }
+        if let (Small(a), Small(b)) = (self, other) {
+            return Some(Small(if *b == -1 { 0 } else { a.mod_floor(b) }));
+        }
+        Some(self.big_op(other, |a, b| a.mod_floor(&b)))
+    }
+
+    #[inline]
+    pub fn pow(&self, exponent: u32) -> Integer {
+        if let Small(a) = self {
+            if let Some(v) = a.checked_pow(exponent) {
+                return Small(v);
+            }
+        }
+        Integer::from_big(Pow::pow(self.to_big(), exponent))
+    }
+
+    #[inline]
+    pub fn neg(&self) -> Integer {
+        if let Small(a) = self {
+            if let Some(v) = a.checked_neg() {
+                return Small(v);
+            }
+        }
+        Integer::from_big(-self.to_big())
+    }
+
+    #[inline]
+    pub fn abs(&self) -> Integer {
+        if let Small(a) = self {
+            if let Some(v) = a.checked_abs() {
+                return Small(v);
+            }
+        }
+        Integer::from_big(self.to_big().abs())
+    }
+
+    pub fn signum(&self) -> Integer {
+        match self {
+            Small(v) => Small(v.signum()),
+            Big(v) if v.is_negative() => Small(-1),
+            Big(_) => Small(1),
+        }
+    }
+}
+
+impl From<i64> for Integer {
+    fn from(value: i64) -> Integer {
+        Small(value)
+    }
+}
+
+impl FromStr for Integer {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Integer, ()> {
+        match s.parse::<i64>() {
+            Ok(v) => Ok(Small(v)),
+            Err(_) => BigInt::from_str(s).map(Integer::from_big).map_err(|_| ()),
+        }
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Integer) -> Ordering {
+        match (self, other) {
+            (Small(a), Small(b)) => a.cmp(b),
+            (a, b) => a.to_big().cmp(&b.to_big()),
+        }
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Integer) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Small(v) => v.fmt(f),
+            Big(v) => v.fmt(f),
+        }
+    }
+}
+
+/// Shows the plain number, since the representation is an implementation
+/// detail.
+impl fmt::Debug for Integer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
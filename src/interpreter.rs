@@ -1,23 +1,68 @@
+use error::{KatError, RuntimeError};
+use integer::Integer;
 use itertools::Itertools;
-use spec::CatCommand;
-use std::collections::HashMap;
+use spec::{CatCommand, Command};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::convert::TryFrom;
+use std::fmt;
 use std::io::{self, BufRead, Write};
 use std::mem;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
-#[allow(dead_code)]
+#[allow(clippy::enum_variant_names)]
 #[derive(Clone, Debug)]
 pub enum CatValue {
-    VInteger(i64),
+    VInteger(Integer),
+    VFloat(f64),
     VString(String),
     VStack(Vec<CatValue>),
-    VCommand(CatCommand),
+    VCommand(Command),
 }
 use self::CatValue::*;
 
 impl CatValue {
+    /// Zero, the empty string and the empty stack are falsy, everything
+    /// else is truthy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            VInteger(v) => !v.is_zero(),
+            VFloat(v) => *v != 0.0,
+            VString(v) => !v.is_empty(),
+            VStack(v) => !v.is_empty(),
+            VCommand(_) => true,
+        }
+    }
+
+    pub fn from_bool(value: bool) -> CatValue {
+        VInteger(Integer::from(value as i64))
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            VInteger(_) => 0,
+            VFloat(_) => 0,
+            VString(_) => 1,
+            VStack(_) => 2,
+            VCommand(_) => 3,
+        }
+    }
+
+    fn to_f64(&self) -> Option<f64> {
+        match self {
+            VInteger(v) => Some(v.to_f64()),
+            VFloat(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Integral floats are printed without a fractional part, eg. `3`.
     pub fn stringify(&self) -> String {
         match self {
             VInteger(v) => v.to_string(),
+            VFloat(v) => v.to_string(),
             VString(v) => v.clone(),
             VStack(v) => format!("[{}]", v.iter().map(|x| x.debug_stringify()).join(" ")),
             VCommand(v) => format!("{:?}", v),
@@ -27,6 +72,7 @@ impl CatValue {
     pub fn debug_stringify(&self) -> String {
         match self {
             VInteger(v) => v.to_string(),
+            VFloat(v) => v.to_string(),
             VString(v) => format!(
                 "\"{}\"",
                 v.chars()
@@ -42,6 +88,38 @@ impl CatValue {
         }
     }
 
+    /// An estimate of the memory used by the value in bytes. Commands are
+    /// counted as a fixed size, since they always come from the program.
+    pub fn approx_size(&self) -> usize {
+        VALUE_SIZE
+            + match self {
+                VInteger(v) => (v.to_i64().map_or(v.bits(), |_| 0) / 8) as usize,
+                VFloat(_) | VCommand(_) => 0,
+                VString(v) => v.len(),
+                VStack(v) => v.iter().map(CatValue::approx_size).sum(),
+            }
+    }
+
+    /// Returns the items of a stack, or the characters of a string as
+    /// separate strings.
+    pub fn into_elements(self, command: &'static str) -> Result<Vec<CatValue>, RuntimeError> {
+        match self {
+            VStack(v) => Ok(v),
+            VString(v) => Ok(v.chars().map(|c| VString(c.to_string())).collect()),
+            x => Err(wrong_type(command, "a stack or a string", &x)),
+        }
+    }
+
+    /// The inverse of `into_elements`: joins the items back into a string if
+    /// they came from one, otherwise collects them to a stack.
+    pub fn from_elements(values: Vec<CatValue>, as_string: bool) -> CatValue {
+        if as_string {
+            VString(values.iter().map(|x| x.stringify()).collect())
+        } else {
+            VStack(values)
+        }
+    }
+
     pub fn auto_map<E>(
         self,
         mut func: impl FnMut(CatValue) -> Result<CatValue, E>,
@@ -62,269 +140,568 @@ impl CatValue {
     ) -> Result<CatValue, E> {
         match self {
             VStack(vec) => Ok(VStack(
-                vec.into_iter()
-                    .map(func)
-                    .collect::<Result<Vec<CatValue>, _>>()?,
+                vec.iter().map(func).collect::<Result<Vec<CatValue>, _>>()?,
             )),
             x => func(x),
         }
     }
 
-    #[allow(dead_code)]
     pub fn auto_do<E>(&self, mut func: impl FnMut(&CatValue) -> Result<(), E>) -> Result<(), E> {
         match self {
-            VStack(vec) => {
-                vec.into_iter().map(func).collect::<Result<(), _>>()?;
-                Ok(())
-            }
+            VStack(vec) => vec.iter().try_for_each(func),
             x => func(x),
         }
     }
 }
 
+const VALUE_SIZE: usize = mem::size_of::<CatValue>();
+
+/// Orders floats numerically, with `¯0.0` equal to `0.0`. NaN is equal to
+/// itself and greater than every other number, so that the order is total.
+fn cmp_floats(a: f64, b: f64) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => Ordering::Equal,
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        (false, false) => a.partial_cmp(&b).unwrap(),
+    }
+}
+
+/// Values of the same type compare naturally, with stacks compared
+/// lexicographically. Integers and floats compare exactly by numeric value,
+/// and NaN sorts after every other number. Values of different types are
+/// ordered as numbers < strings < stacks < commands.
+impl Ord for CatValue {
+    fn cmp(&self, other: &CatValue) -> Ordering {
+        match (self, other) {
+            (VInteger(a), VInteger(b)) => a.cmp(b),
+            (VFloat(a), VFloat(b)) => cmp_floats(*a, *b),
+            (VInteger(a), VFloat(b)) => a.cmp_f64(*b),
+            (VFloat(a), VInteger(b)) => b.cmp_f64(*a).reverse(),
+            (VString(a), VString(b)) => a.cmp(b),
+            (VStack(a), VStack(b)) => a.cmp(b),
+            (VCommand(a), VCommand(b)) => format!("{:?}", a).cmp(&format!("{:?}", b)),
+            (a, b) => a.type_rank().cmp(&b.type_rank()),
+        }
+    }
+}
+
+impl PartialEq for CatValue {
+    fn eq(&self, other: &CatValue) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for CatValue {}
+
+impl PartialOrd for CatValue {
+    fn partial_cmp(&self, other: &CatValue) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct ExecFrame {
     pub stack_before: Vec<CatValue>,
     pub stack_after: Vec<CatValue>,
-    pub reading: bool,
-    pub command: CatCommand,
+    pub command: Command,
     pub inner_frames: Vec<ExecFrame>,
 }
 
-#[derive(Debug)]
+/// An output handle that collects everything written to it into a shared
+/// buffer, so the output can be inspected after the interpreter is done.
+#[derive(Clone, Debug, Default)]
+pub struct CapturedOutput(Rc<RefCell<Vec<u8>>>);
+
+impl CapturedOutput {
+    pub fn new() -> CapturedOutput {
+        CapturedOutput::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for CapturedOutput {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Resource limits for running untrusted programs. `None` means unlimited.
+/// By default, only the depth is limited.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    /// The total number of commands executed, including commands inside
+    /// blocks. Each execution of a block counts as a step too.
+    pub max_steps: Option<u64>,
+    /// How long the program may run, counted from when the limits are set.
+    pub timeout: Option<Duration>,
+    /// The approximate memory used by the values on the stacks and in
+    /// variables, in bytes.
+    pub max_memory: Option<usize>,
+    /// How deeply blocks can execute other blocks, and lists can be nested.
+    /// Execution recurses on the native stack, taking up to about 2 KiB per
+    /// level in release builds and 6 KiB in debug builds. The default of
+    /// `DEFAULT_MAX_DEPTH` fits in the 8 MiB stack of a main thread; deeper
+    /// limits need a thread with a larger stack. Without a limit, deep
+    /// recursion overflows the stack and aborts the process.
+    pub max_depth: Option<usize>,
+}
+
+/// The depth limit of `Limits::default()`.
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+
+impl Default for Limits {
+    fn default() -> Limits {
+        Limits {
+            max_steps: None,
+            timeout: None,
+            max_memory: None,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+        }
+    }
+}
+
 pub struct Interpreter {
     pub exec_frames: Vec<ExecFrame>,
     pub main_stack: Vec<CatValue>,
     pub side_stack: Vec<CatValue>,
     pub variables: HashMap<char, CatValue>,
-    block_stack: Option<Vec<CatValue>>,
-    block_depth: i64,
+    input: Box<dyn BufRead>,
+    output: Box<dyn Write>,
+    steps: u64,
+    max_steps: Option<u64>,
+    deadline: Option<Instant>,
+    memory: usize,
+    max_memory: Option<usize>,
+    depth: usize,
+    max_depth: Option<usize>,
     collect_frame_pos: usize,
     trace: bool,
 }
 
+/// How many steps are executed between checks of the deadline, so that the
+/// clock isn't read for every command.
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+impl fmt::Debug for Interpreter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interpreter")
+            .field("exec_frames", &self.exec_frames)
+            .field("main_stack", &self.main_stack)
+            .field("side_stack", &self.side_stack)
+            .field("variables", &self.variables)
+            .field("steps", &self.steps)
+            .field("max_steps", &self.max_steps)
+            .field("deadline", &self.deadline)
+            .field("memory", &self.memory)
+            .field("max_memory", &self.max_memory)
+            .field("depth", &self.depth)
+            .field("max_depth", &self.max_depth)
+            .field("collect_frame_pos", &self.collect_frame_pos)
+            .field("trace", &self.trace)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Interpreter {
+    /// Creates an interpreter that reads from stdin and writes to stdout.
     pub fn new(trace: bool) -> Interpreter {
+        Interpreter::with_io(
+            trace,
+            Box::new(io::BufReader::new(io::stdin())),
+            Box::new(io::stdout()),
+        )
+    }
+
+    pub fn with_io(trace: bool, input: Box<dyn BufRead>, output: Box<dyn Write>) -> Interpreter {
         Interpreter {
             main_stack: Vec::new(),
             side_stack: Vec::new(),
             variables: HashMap::new(),
-            block_stack: None,
-            block_depth: 0,
+            input,
+            output,
+            steps: 0,
+            max_steps: None,
+            deadline: None,
+            memory: 0,
+            max_memory: None,
+            depth: 0,
+            max_depth: None,
             exec_frames: vec![],
             collect_frame_pos: 0,
-            trace: trace,
+            trace,
         }
     }
 
-    #[allow(dead_code)]
+    /// Applies all of `limits`. The timeout starts counting now.
+    pub fn set_limits(&mut self, limits: &Limits) {
+        self.set_max_steps(limits.max_steps);
+        self.set_deadline(limits.timeout.map(|timeout| Instant::now() + timeout));
+        self.set_max_memory(limits.max_memory);
+        self.set_max_depth(limits.max_depth);
+    }
+
+    /// Limits the total number of commands executed, including commands
+    /// inside blocks.
+    pub fn set_max_steps(&mut self, max_steps: Option<u64>) {
+        self.max_steps = max_steps;
+    }
+
+    /// Aborts execution once the deadline has passed.
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    /// Limits the approximate memory used by the values on the stacks and in
+    /// variables. Values are measured as they move on and off the stacks, so
+    /// with a limit, moving a large list costs time proportional to its size.
+    pub fn set_max_memory(&mut self, max_memory: Option<usize>) {
+        self.max_memory = max_memory;
+        // Values aren't counted without a limit, so count what's there now.
+        self.memory = if max_memory.is_some() {
+            let values = self.main_stack.iter().chain(&self.side_stack);
+            values
+                .chain(self.variables.values())
+                .map(CatValue::approx_size)
+                .sum()
+        } else {
+            0
+        };
+    }
+
+    /// Limits how deeply blocks can execute other blocks, and lists can be
+    /// nested. See `Limits::max_depth` for the stack this needs.
+    pub fn set_max_depth(&mut self, max_depth: Option<usize>) {
+        self.max_depth = max_depth;
+    }
+
+    /// The number of commands executed so far.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
     pub fn execute<'a>(
         &mut self,
-        commands: impl Iterator<Item = &'a CatCommand>,
-    ) -> Result<(), String> {
+        commands: impl Iterator<Item = &'a Command>,
+    ) -> Result<(), KatError> {
         for command in commands {
             self.execute_single(command)?;
         }
         Ok(())
     }
 
-    pub fn execute_single(&mut self, command: &CatCommand) -> Result<(), String> {
-        if self.trace {
-            let stack_before = self.main_stack.clone();
-            let frame_len = self.exec_frames.len();
+    pub fn execute_single(&mut self, command: &Command) -> Result<(), KatError> {
+        let result = self.check_limits().map_err(KatError::from).and_then(|()| {
+            if self.trace {
+                self.execute_traced(command)
+            } else {
+                self.execute_single_f(command)
+            }
+        });
+        result
+            .and_then(|()| self.reserve(0).map_err(KatError::from))
+            .map_err(|e| e.or_span(command.span))
+    }
 
-            self.execute_single_f(command)?;
+    /// Executes a command and records a frame of it.
+    #[inline(never)]
+    fn execute_traced(&mut self, command: &Command) -> Result<(), KatError> {
+        let stack_before = self.main_stack.clone();
+        let frame_len = self.exec_frames.len();
 
-            let inner_frames = self.exec_frames.drain(frame_len..).collect();
+        self.execute_single_f(command)?;
 
-            self.exec_frames.push(ExecFrame {
-                stack_before: stack_before,
-                stack_after: self.main_stack.clone(),
-                reading: self.block_stack.is_some(),
-                command: command.clone(),
-                inner_frames: inner_frames,
-            });
-        } else {
-            self.execute_single_f(command)?;
-        }
+        let inner_frames = self.exec_frames.drain(frame_len..).collect();
+
+        self.exec_frames.push(ExecFrame {
+            stack_before,
+            stack_after: self.main_stack.clone(),
+            command: command.clone(),
+            inner_frames,
+        });
         Ok(())
     }
 
-    pub fn execute_single_f(&mut self, command: &CatCommand) -> Result<(), String> {
-        let block_done = if let Some(ref mut bl) = self.block_stack {
-            match command {
-                CatCommand::StartBlock => {
-                    bl.push(VCommand(CatCommand::StartBlock));
-                    self.block_depth += 1;
-                    return Ok(());
-                }
-                CatCommand::CloseBlock => {
-                    self.block_depth -= 1;
-                    if self.block_depth == 0 {
-                        true
-                    } else {
-                        bl.push(VCommand(CatCommand::CloseBlock));
-                        return Ok(());
-                    }
-                }
-                cmd => {
-                    bl.push(VCommand(cmd.clone()));
-                    return Ok(());
-                }
-            }
-        } else {
-            false
-        };
-        if block_done {
-            let bl = self.block_stack.take().unwrap();
-            self.push(VStack(bl));
-            return Ok(());
+    pub fn execute_single_f(&mut self, command: &Command) -> Result<(), KatError> {
+        // Only the commands that execute functions are matched here, and the
+        // rest in `execute_plain`. Executing nested blocks recurses through
+        // this function, so its stack frame is kept small.
+        match &command.kind {
+            CatCommand::List(commands) => self.run_list(commands),
+            CatCommand::Execute => self.run_execute(),
+            CatCommand::Map => self.run_map(),
+            CatCommand::ForEach => self.run_for_each(),
+            CatCommand::Filter => self.run_filter(),
+            CatCommand::Fold => self.run_fold(),
+            CatCommand::Scan => self.run_scan(),
+            CatCommand::SortBy => self.run_sort_by(),
+            CatCommand::Repeat => self.run_repeat(),
+            CatCommand::If => self.run_if(),
+            CatCommand::IfElse => self.run_if_else(),
+            CatCommand::While => self.run_while(),
+            CatCommand::Until => self.run_until(),
+            CatCommand::FixedPoint => self.run_fixed_point(),
+            CatCommand::PopVariable(c, execute) => self.run_pop_variable(*c, *execute),
+            kind => self.execute_plain(kind),
         }
+    }
+
+    /// Executes a command that doesn't execute functions.
+    #[inline(never)]
+    fn execute_plain(&mut self, command: &CatCommand) -> Result<(), KatError> {
         match command {
-            CatCommand::StartBlock => {
-                self.block_stack = Some(Vec::new());
-                self.block_depth += 1;
-            }
-            CatCommand::CloseBlock => {
-                return Err("Closing outside a block".to_owned());
+            CatCommand::Block(commands) => {
+                self.push(VStack(commands.iter().cloned().map(VCommand).collect()));
             }
             CatCommand::CreateString(v) => self.push(VString(v.clone())),
-            &CatCommand::CreateInteger(v) => self.push(VInteger(v)),
+            CatCommand::CreateInteger(v) => self.push(VInteger(v.clone())),
+            CatCommand::CreateFloat(v) => self.push(VFloat(*v)),
             CatCommand::CreateCommand(v) => self.push(VCommand(*v.clone())),
             CatCommand::ReadLine => {
-                io::stdout().flush().ok().expect("Could not flush stdout");
+                self.output.flush().map_err(io_error)?;
                 let mut line = String::new();
-                let stdin = io::stdin();
-                stdin.lock().read_line(&mut line).unwrap();
-                line.pop();
+                if self.input.read_line(&mut line).map_err(io_error)? == 0 {
+                    return Err(RuntimeError::EndOfInput.into());
+                }
+                if line.ends_with('\n') {
+                    line.pop();
+                    if line.ends_with('\r') {
+                        line.pop();
+                    }
+                }
                 self.push(VString(line));
             }
             CatCommand::WriteLine => {
-                println!("{}", self.pop_res()?.stringify());
+                let val = self.pop_res()?;
+                writeln!(self.output, "{}", val.stringify()).map_err(io_error)?;
             }
             CatCommand::Write => {
-                print!("{}", self.pop_res()?.stringify());
+                let val = self.pop_res()?;
+                write!(self.output, "{}", val.stringify()).map_err(io_error)?;
             }
             CatCommand::Add => self.run_add()?,
+            CatCommand::Subtract => {
+                self.run_arithmetic("Subtract", |a, b| Ok(VInteger(a.sub(b))), |a, b| a - b)?
+            }
             CatCommand::Multiply => {
-                let v1 = self.pop_res()?;
-                let v2 = self.pop_res()?;
-                let val = v1.auto_map(|v1| match v1 {
-                    VInteger(v1) => v2.auto_map_ref(|v2| match v2 {
-                        VInteger(v2) => Ok(VInteger(v1 * v2)),
-                        x => Err(format!("Not a multiplicative type: {:?}", x)),
-                    }),
-                    x => Err(format!("Not a multiplicative type: {:?}", x)),
-                })?;
-                self.push(val);
+                self.run_arithmetic("Multiply", |a, b| Ok(VInteger(a.mul(b))), |a, b| a * b)?
             }
-            CatCommand::Execute => {
-                let val = self.pop_res()?;
-                self.execute_value(&val)?;
+            CatCommand::Divide => self.run_arithmetic(
+                "Divide",
+                |a, b| {
+                    a.floor_div(b)
+                        .map(VInteger)
+                        .ok_or(RuntimeError::DivisionByZero)
+                },
+                |a, b| a / b,
+            )?,
+            CatCommand::Modulo => self.run_arithmetic(
+                "Modulo",
+                |a, b| {
+                    a.floor_mod(b)
+                        .map(VInteger)
+                        .ok_or(RuntimeError::DivisionByZero)
+                },
+                |a, b| a - b * (a / b).floor(),
+            )?,
+            CatCommand::Power => {
+                let budget = self.budget();
+                self.run_arithmetic(
+                    "Power",
+                    |a, b| match b.to_i64() {
+                        Some(e) if e < 0 => Ok(VFloat(a.to_f64().powf(e as f64))),
+                        Some(e) if e <= u32::MAX as i64 => {
+                            let bits = a.bits().saturating_sub(1).saturating_mul(e as u64);
+                            budget.reserve(usize::try_from(bits / 8).unwrap_or(usize::MAX))?;
+                            Ok(VInteger(a.pow(e as u32)))
+                        }
+                        _ => Err(RuntimeError::Overflow("Power")),
+                    },
+                    f64::powf,
+                )?
             }
-            CatCommand::ExecuteScoped => {
-                let val = self.pop_res()?;
-                self.collect_frame(|this| -> Result<(), String> {
-                    this.execute_value(&val)?;
-                    Ok(())
-                })?;
+            CatCommand::Min => {
+                self.run_arithmetic("Min", |a, b| Ok(VInteger(a.min(b).clone())), f64::min)?
             }
-            CatCommand::Map => {
-                let func = self.pop_res()?;
-                let values = match self.pop_res()? {
-                    VStack(v) => v,
-                    VString(v) => v.chars().map(|c| VString(c.to_string())).collect(),
-                    _ => return Err("Map parameter isn't a stack or a string".to_owned()),
-                };
-                let mut results = Vec::new();
-                for val in values {
-                    self.push(val);
-                    self.execute_value(&func)?;
-                    let result = self.pop_res()?;
-                    if let VStack(mut s) = result {
-                        if s.len() == 1 {
-                            results.push(s.swap_remove(0));
-                        } else {
-                            results.push(VStack(s));
-                        }
-                    } else {
-                        results.push(result);
-                    }
-                }
-                self.push(VStack(results));
-            }
-            CatCommand::ForEach => {
-                let func = self.pop_res()?;
-                let values = match self.pop_res()? {
-                    VStack(v) => v,
-                    VString(v) => v.chars().map(|c| VString(c.to_string())).collect(),
-                    _ => return Err("ForEach parameter isn't a stack or a string".to_owned()),
-                };
-                for val in values {
-                    self.push(val);
-                    self.execute_value(&func)?;
+            CatCommand::Max => {
+                self.run_arithmetic("Max", |a, b| Ok(VInteger(a.max(b).clone())), f64::max)?
+            }
+            CatCommand::Negate => self.run_unary("Negate", Integer::neg, |v| -v)?,
+            CatCommand::Abs => self.run_unary("Abs", Integer::abs, f64::abs)?,
+            CatCommand::Sign => self.run_unary("Sign", Integer::signum, |v| {
+                if v == 0.0 {
+                    0.0
+                } else {
+                    v.signum()
                 }
+            })?,
+            CatCommand::SquareRoot => self.replace_top(|val| {
+                val.auto_map_ref(|x| match x.to_f64() {
+                    Some(v) => Ok(VFloat(v.sqrt())),
+                    None => Err(wrong_type("SquareRoot", "a number", x)),
+                })
+            })?,
+            CatCommand::ToFloat => self.replace_top(|val| {
+                val.auto_map_ref(|x| match x {
+                    VString(v) => Ok(VFloat(
+                        v.trim()
+                            .parse()
+                            .map_err(|_| RuntimeError::NotANumber(v.clone()))?,
+                    )),
+                    x => match x.to_f64() {
+                        Some(v) => Ok(VFloat(v)),
+                        None => Err(wrong_type("ToFloat", "a number or a string", x)),
+                    },
+                })
+            })?,
+            CatCommand::Sort => {
+                let list = self.pop_res()?;
+                let is_string = matches!(list, VString(_));
+                let mut values = list.into_elements("Sort")?;
+                values.sort();
+                self.push(CatValue::from_elements(values, is_string));
             }
-            CatCommand::Repeat => {
-                let func = self.pop_res()?;
-                let count = self.pop_res()?;
-                count.auto_do(|count| match count {
-                    VInteger(count) => {
-                        for _ in 0..*count {
-                            self.execute_value(&func)?;
-                        }
-                        Ok(())
-                    }
-                    VString(count) => {
-                        for _ in 0..count.parse().map_err(|_| "Not a number")? {
-                            self.execute_value(&func)?;
-                        }
-                        Ok(())
+            CatCommand::Group => {
+                let list = self.pop_res()?;
+                let is_string = matches!(list, VString(_));
+                let mut groups: Vec<Vec<CatValue>> = Vec::new();
+                for val in list.into_elements("Group")? {
+                    match groups.last_mut() {
+                        Some(group) if group[0] == val => group.push(val),
+                        _ => groups.push(vec![val]),
                     }
-                    x => Err(format!("Not an integer: {:?}", x)),
-                })?;
+                }
+                self.push(VStack(
+                    groups
+                        .into_iter()
+                        .map(|group| CatValue::from_elements(group, is_string))
+                        .collect(),
+                ));
+            }
+            CatCommand::Count => {
+                let item = self.pop_res()?;
+                let values = self.pop_res()?.into_elements("Count")?;
+                let count = values.iter().filter(|val| **val == item).count();
+                self.push(VInteger(Integer::from(count as i64)));
+            }
+            CatCommand::Unique => {
+                let list = self.pop_res()?;
+                let is_string = matches!(list, VString(_));
+                let mut seen = BTreeSet::new();
+                let values = list
+                    .into_elements("Unique")?
+                    .into_iter()
+                    .filter(|val| seen.insert(val.clone()))
+                    .collect();
+                self.push(CatValue::from_elements(values, is_string));
+            }
+            CatCommand::Equal => self.run_compare(|a, b| a == b)?,
+            CatCommand::Less => self.run_compare(|a, b| a < b)?,
+            CatCommand::Greater => self.run_compare(|a, b| a > b)?,
+            // Boolean commands test whole values, like conditionals do, so an
+            // empty list is false rather than mapped over.
+            CatCommand::And => {
+                let b = self.pop_res()?;
+                let a = self.pop_res()?;
+                self.push(CatValue::from_bool(a.is_truthy() && b.is_truthy()));
+            }
+            CatCommand::Or => {
+                let b = self.pop_res()?;
+                let a = self.pop_res()?;
+                self.push(CatValue::from_bool(a.is_truthy() || b.is_truthy()));
+            }
+            CatCommand::Not => {
+                let val = self.pop_res()?;
+                self.push(CatValue::from_bool(!val.is_truthy()));
             }
             CatCommand::Split => {
                 let separator = match self.pop_res()? {
                     VString(v) => v,
-                    _ => return Err("Split parameter isn't a string".to_owned()),
+                    x => return Err(wrong_type("Split", "a string", &x).into()),
                 };
-                let val = self.main_stack.last_mut().ok_or_else(|| "Empty stack")?;
-                *val = val.auto_map_ref(|x| match x {
-                    VString(v) => Ok(VStack(
-                        v.split(&separator).map(|x| VString(x.to_owned())).collect(),
-                    )),
-                    _ => Err("Split parameter isn't a string".to_owned()),
+                self.replace_top(|val| {
+                    val.auto_map_ref(|x| match x {
+                        VString(v) => Ok(VStack(
+                            v.split(&separator).map(|x| VString(x.to_owned())).collect(),
+                        )),
+                        x => Err(wrong_type("Split", "a string", x)),
+                    })
                 })?;
             }
             CatCommand::Join => {
                 let separator = match self.pop_res()? {
                     VString(v) => v,
-                    _ => return Err("Join parameter isn't a string".to_owned()),
-                };
-                let val = self.main_stack.last_mut().ok_or_else(|| "Empty stack")?;
-                *val = match val {
-                    VStack(ref v) => VString(v.iter().map(|x| x.stringify()).join(&separator)),
-                    VString(ref v) => VString(v.chars().join(&separator)),
-                    _ => return Err("Join parameter isn't a stack".to_owned()),
+                    x => return Err(wrong_type("Join", "a string", &x).into()),
                 };
+                let budget = self.budget();
+                self.replace_top(|val| {
+                    let count = match val {
+                        VStack(v) => v.len(),
+                        VString(v) => v.chars().count(),
+                        x => return Err(wrong_type("Join", "a stack", x)),
+                    };
+                    budget.reserve(separator.len().saturating_mul(count))?;
+                    Ok(match val {
+                        VStack(v) => VString(v.iter().map(|x| x.stringify()).join(&separator)),
+                        VString(v) => VString(v.chars().join(&separator)),
+                        _ => unreachable!(),
+                    })
+                })?;
             }
-            CatCommand::ToInteger => {
-                let val = self.main_stack.last_mut().ok_or_else(|| "Empty stack")?;
-                *val = val.auto_map_ref(|x| match x {
-                    VInteger(v) => Ok(VInteger(*v)),
+            CatCommand::ToInteger => self.replace_top(|val| {
+                val.auto_map_ref(|x| match x {
+                    VInteger(v) => Ok(VInteger(v.clone())),
+                    VFloat(v) => {
+                        Ok(VInteger(Integer::from_f64(*v).ok_or_else(|| {
+                            RuntimeError::NotAnInteger(v.to_string())
+                        })?))
+                    }
                     VString(v) => Ok(VInteger(
                         v.parse()
-                            .map_err(|_| "String doesn't represent an integer")?,
+                            .map_err(|_| RuntimeError::NotAnInteger(v.clone()))?,
                     )),
-                    _ => Err("Can't convert value to string".to_owned()),
-                })?;
-            }
+                    x => Err(wrong_type("ToInteger", "a number or a string", x)),
+                })
+            })?,
             CatCommand::Range => {
-                let count = self.main_stack.last_mut().ok_or_else(|| "Empty stack")?;
-                *count = count.auto_map_ref(|end| match end {
-                    VInteger(end) => Ok(VStack((1..end + 1).map(VInteger).collect())),
-                    _ => Err("Range requires integer parameters"),
+                let mut len: usize = 0;
+                self.main_stack
+                    .last()
+                    .ok_or(RuntimeError::EmptyStack)?
+                    .auto_do(|end| -> Result<(), RuntimeError> {
+                        if let VInteger(end) = end {
+                            let end =
+                                usize::try_from(end.clamp_to_i64().max(0)).unwrap_or(usize::MAX);
+                            len = len.saturating_add(end);
+                        }
+                        Ok(())
+                    })?;
+                self.reserve(len.saturating_mul(VALUE_SIZE))?;
+                self.replace_top(|count| {
+                    count.auto_map_ref(|end| match end {
+                        VInteger(end) => Ok(VStack(match end.to_i64() {
+                            Some(end) => (1..=end).map(|v| VInteger(Integer::from(v))).collect(),
+                            None => {
+                                let mut values = Vec::new();
+                                let mut v = Integer::from(1);
+                                while &v <= end {
+                                    let next = v.add(&Integer::from(1));
+                                    values.push(VInteger(v));
+                                    v = next;
+                                }
+                                values
+                            }
+                        })),
+                        x => Err(wrong_type("Range", "an integer", x)),
+                    })
                 })?;
             }
             CatCommand::Duplicate => {
@@ -339,63 +716,288 @@ impl Interpreter {
             CatCommand::Drop => {
                 self.pop_res()?;
             }
-            &CatCommand::Rotate(n) => {
+            CatCommand::Rotate(rotation) => {
+                let n = rotation.depth();
                 for i in 0..n {
                     self.swap(n - i - 1, 0)?;
                 }
             }
             CatCommand::PushSide => {
                 let item = self.copy_nth(0)?;
+                self.track(&item);
                 self.side_stack.push(item);
             }
             CatCommand::PopSide => {
-                let item = self
-                    .side_stack
-                    .pop()
-                    .ok_or_else(|| "Pop from empty side stack")?;
+                let item = self.side_stack.pop().ok_or(RuntimeError::EmptySideStack)?;
+                self.untrack(&item);
                 self.push(item);
             }
             CatCommand::ConsumeSide => {
                 let mut new_stack = Vec::new();
                 mem::swap(&mut new_stack, &mut self.side_stack);
-                self.push(VStack(new_stack));
+                // The moved values are already accounted for.
+                self.memory += VALUE_SIZE;
+                self.main_stack.push(VStack(new_stack));
             }
-            &CatCommand::PushVariable(c) => {
+            CatCommand::Native(native) => native.call(self)?,
+            CatCommand::PushVariable(c) => {
                 let item = self.pop_res()?;
-                self.variables.insert(c, item);
-            }
-            &CatCommand::PopVariable(c, execute) => {
-                let item = self
-                    .variables
-                    .get(&c)
-                    .ok_or_else(|| "Fetched from an empty variable")?
-                    .clone();
-                if execute {
-                    match item {
-                        VCommand(_) => self.execute_value(&item)?,
-                        VStack(_) => self.execute_value(&item)?,
-                        _ => self.push(item),
-                    }
-                } else {
-                    self.push(item);
+                self.track(&item);
+                if let Some(old) = self.variables.insert(*c, item) {
+                    self.untrack(&old);
                 }
             }
+            _ => unreachable!("Commands that execute functions are matched in execute_single_f"),
         };
         Ok(())
     }
 
-    fn execute_value(&mut self, value: &CatValue) -> Result<(), String> {
+    fn run_execute(&mut self) -> Result<(), KatError> {
+        let val = self.pop_res()?;
+        self.execute_value(&val)?;
+        Ok(())
+    }
+
+    fn run_map(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let values = self.pop_res()?.into_elements("Map")?;
+        let mut results = Vec::new();
+        for val in values {
+            self.push(val);
+            self.execute_value(&func)?;
+            let result = self.pop_res()?;
+            if let VStack(mut s) = result {
+                if s.len() == 1 {
+                    results.push(s.swap_remove(0));
+                } else {
+                    results.push(VStack(s));
+                }
+            } else {
+                results.push(result);
+            }
+        }
+        self.push(VStack(results));
+        Ok(())
+    }
+
+    fn run_for_each(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let values = self.pop_res()?.into_elements("ForEach")?;
+        for val in values {
+            self.push(val);
+            self.execute_value(&func)?;
+        }
+        Ok(())
+    }
+
+    fn run_filter(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let list = self.pop_res()?;
+        let is_string = matches!(list, VString(_));
+        let mut results = Vec::new();
+        for val in list.into_elements("Filter")? {
+            self.push(val.clone());
+            self.execute_value(&func)?;
+            if self.pop_res()?.is_truthy() {
+                results.push(val);
+            }
+        }
+        self.push(CatValue::from_elements(results, is_string));
+        Ok(())
+    }
+
+    fn run_fold(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let mut values = self.pop_res()?.into_elements("Fold")?.into_iter();
+        self.push(values.next().ok_or(RuntimeError::EmptyFold)?);
+        for val in values {
+            self.push(val);
+            self.execute_value(&func)?;
+        }
+        Ok(())
+    }
+
+    fn run_scan(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let mut values = self.pop_res()?.into_elements("Scan")?.into_iter();
+        let mut results = Vec::new();
+        if let Some(first) = values.next() {
+            results.push(first.clone());
+            self.push(first);
+            for val in values {
+                self.push(val);
+                self.execute_value(&func)?;
+                results.push(self.copy_nth(0)?);
+            }
+            self.pop_res()?;
+        }
+        self.push(VStack(results));
+        Ok(())
+    }
+
+    fn run_sort_by(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let list = self.pop_res()?;
+        let is_string = matches!(list, VString(_));
+        let mut keyed = Vec::new();
+        for val in list.into_elements("SortBy")? {
+            self.push(val.clone());
+            self.execute_value(&func)?;
+            keyed.push((self.pop_res()?, val));
+        }
+        keyed.sort_by(|a, b| a.0.cmp(&b.0));
+        let values = keyed.into_iter().map(|(_, val)| val).collect();
+        self.push(CatValue::from_elements(values, is_string));
+        Ok(())
+    }
+
+    fn run_repeat(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        let count = self.pop_res()?;
+        count.auto_do(|count| -> Result<(), KatError> {
+            match count {
+                VInteger(count) => {
+                    for _ in 0..count.clamp_to_i64() {
+                        self.execute_value(&func)?;
+                    }
+                    Ok(())
+                }
+                VString(count) => {
+                    let count: Integer = count
+                        .parse()
+                        .map_err(|_| RuntimeError::NotAnInteger(count.clone()))?;
+                    for _ in 0..count.clamp_to_i64() {
+                        self.execute_value(&func)?;
+                    }
+                    Ok(())
+                }
+                x => Err(wrong_type("Repeat", "an integer", x).into()),
+            }
+        })?;
+        Ok(())
+    }
+
+    fn run_if(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        if self.pop_res()?.is_truthy() {
+            self.execute_value(&func)?;
+        }
+        Ok(())
+    }
+
+    fn run_if_else(&mut self) -> Result<(), KatError> {
+        let else_func = self.pop_res()?;
+        let then_func = self.pop_res()?;
+        if self.pop_res()?.is_truthy() {
+            self.execute_value(&then_func)?;
+        } else {
+            self.execute_value(&else_func)?;
+        }
+        Ok(())
+    }
+
+    fn run_while(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        while self.pop_res()?.is_truthy() {
+            self.execute_value(&func)?;
+        }
+        Ok(())
+    }
+
+    fn run_until(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        while !self.pop_res()?.is_truthy() {
+            self.execute_value(&func)?;
+        }
+        Ok(())
+    }
+
+    fn run_fixed_point(&mut self) -> Result<(), KatError> {
+        let func = self.pop_res()?;
+        loop {
+            let before = self.copy_nth(0)?;
+            self.execute_value(&func)?;
+            if self.copy_nth(0)? == before {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn run_pop_variable(&mut self, c: char, execute: bool) -> Result<(), KatError> {
+        let item = self
+            .variables
+            .get(&c)
+            .ok_or(RuntimeError::EmptyVariable(c))?
+            .clone();
+        if execute {
+            match item {
+                VCommand(_) => self.execute_value(&item)?,
+                VStack(_) => self.execute_value(&item)?,
+                _ => self.push(item),
+            }
+        } else {
+            self.push(item);
+        }
+        Ok(())
+    }
+
+    fn check_limits(&mut self) -> Result<(), RuntimeError> {
+        self.steps += 1;
+        if let Some(max_steps) = self.max_steps {
+            if self.steps > max_steps {
+                return Err(RuntimeError::StepLimitExceeded(max_steps));
+            }
+        }
+        if let Some(deadline) = self.deadline {
+            if self.steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) && Instant::now() >= deadline {
+                return Err(RuntimeError::Timeout);
+            }
+        }
+        Ok(())
+    }
+
+    /// Executes a function. Each call counts as a step, so that loops over
+    /// an empty block still run into the step limit and the timeout.
+    fn execute_value(&mut self, value: &CatValue) -> Result<(), KatError> {
+        self.check_limits()?;
+        self.enter()?;
+        let result = self.execute_value_f(value);
+        self.depth -= 1;
+        result
+    }
+
+    /// Goes one level deeper in executing nested blocks and lists. The caller
+    /// decrements the depth when done.
+    fn enter(&mut self) -> Result<(), RuntimeError> {
+        if let Some(max_depth) = self.max_depth {
+            if self.depth >= max_depth {
+                return Err(RuntimeError::DepthLimitExceeded(max_depth));
+            }
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn run_list(&mut self, commands: &[Command]) -> Result<(), KatError> {
+        self.enter()?;
+        let result = self.collect_frame(|this| this.execute(commands.iter()));
+        self.depth -= 1;
+        result
+    }
+
+    fn execute_value_f(&mut self, value: &CatValue) -> Result<(), KatError> {
         match value {
             VStack(cmds) => {
                 for cmd in cmds {
                     match cmd {
                         VCommand(c) => self.execute_single(c)?,
-                        _ => return Err("Executed stack has non-command values".to_owned()),
+                        x => return Err(RuntimeError::NotExecutable(x.debug_stringify()).into()),
                     };
                 }
             }
             VCommand(cmd) => self.execute_single(cmd)?,
-            _ => return Err("Can't execute".to_owned()),
+            x => return Err(RuntimeError::NotExecutable(x.debug_stringify()).into()),
         }
         Ok(())
     }
@@ -412,11 +1014,40 @@ impl Interpreter {
             .drain(self.collect_frame_pos - 1..)
             .collect();
         self.collect_frame_pos = orig_pos;
-        self.push(VStack(result));
-        return Ok(out);
+        // The collected values are already accounted for.
+        self.memory += VALUE_SIZE;
+        self.main_stack.push(VStack(result));
+        Ok(out)
+    }
+
+    /// Fails if `size` more bytes would exceed the memory limit.
+    fn reserve(&self, size: usize) -> Result<(), RuntimeError> {
+        self.budget().reserve(size)
+    }
+
+    fn budget(&self) -> MemoryBudget {
+        MemoryBudget {
+            used: self.memory,
+            limit: self.max_memory,
+        }
     }
 
-    fn push(&mut self, val: CatValue) {
+    /// Counts a value towards the memory limit. Measuring a value walks all
+    /// of it, so nothing is counted without a limit.
+    fn track(&mut self, val: &CatValue) {
+        if self.max_memory.is_some() {
+            self.memory += val.approx_size();
+        }
+    }
+
+    fn untrack(&mut self, val: &CatValue) {
+        if self.max_memory.is_some() {
+            self.memory = self.memory.saturating_sub(val.approx_size());
+        }
+    }
+
+    pub fn push(&mut self, val: CatValue) {
+        self.track(&val);
         self.main_stack.push(val)
     }
 
@@ -424,31 +1055,112 @@ impl Interpreter {
         if self.main_stack.len() < self.collect_frame_pos {
             self.collect_frame_pos = self.main_stack.len();
         }
-        self.main_stack.pop()
+        let val = self.main_stack.pop()?;
+        self.untrack(&val);
+        Some(val)
     }
 
-    fn pop_res(&mut self) -> Result<CatValue, &'static str> {
-        self.pop().ok_or("Pop from an empty stack")
+    /// Replaces the top of the stack with the result of `func`. The stack is
+    /// left untouched if `func` fails.
+    fn replace_top(
+        &mut self,
+        func: impl FnOnce(&CatValue) -> Result<CatValue, RuntimeError>,
+    ) -> Result<(), RuntimeError> {
+        let val = func(self.main_stack.last().ok_or(RuntimeError::EmptyStack)?)?;
+        self.track(&val);
+        let old = mem::replace(self.top_mut()?, val);
+        self.untrack(&old);
+        Ok(())
     }
 
-    fn copy_nth(&mut self, n: usize) -> Result<CatValue, &'static str> {
+    pub fn pop_res(&mut self) -> Result<CatValue, RuntimeError> {
+        self.pop().ok_or(RuntimeError::EmptyStack)
+    }
+
+    fn top_mut(&mut self) -> Result<&mut CatValue, RuntimeError> {
+        self.main_stack.last_mut().ok_or(RuntimeError::EmptyStack)
+    }
+
+    fn copy_nth(&mut self, n: usize) -> Result<CatValue, RuntimeError> {
         if self.main_stack.len() <= n {
-            Err("pop from an empty stack")
+            Err(RuntimeError::EmptyStack)
         } else {
             Ok(self.main_stack[self.main_stack.len() - n - 1].clone())
         }
     }
 
-    fn swap(&mut self, n1: usize, n2: usize) -> Result<(), &'static str> {
+    fn swap(&mut self, n1: usize, n2: usize) -> Result<(), RuntimeError> {
         let len = self.main_stack.len();
         if len <= n1 || len <= n2 {
-            Err("pop from an empty stack")
+            Err(RuntimeError::EmptyStack)
         } else {
-            Ok(self.main_stack.swap(len - n1 - 1, len - n2 - 1))
+            self.main_stack.swap(len - n1 - 1, len - n2 - 1);
+            Ok(())
         }
     }
 
-    fn run_add(&mut self) -> Result<(), String> {
+    /// Pops two values and pushes the result of comparing them. If exactly
+    /// one of the values is a stack, the comparison is applied to each item.
+    fn run_compare(
+        &mut self,
+        func: impl Fn(&CatValue, &CatValue) -> bool,
+    ) -> Result<(), RuntimeError> {
+        let b = self.pop_res()?;
+        let a = self.pop_res()?;
+        let result = match (a, b) {
+            (VStack(a), b) if !matches!(b, VStack(_)) => {
+                VStack(a.iter().map(|a| CatValue::from_bool(func(a, &b))).collect())
+            }
+            (a, VStack(b)) if !matches!(a, VStack(_)) => {
+                VStack(b.iter().map(|b| CatValue::from_bool(func(&a, b))).collect())
+            }
+            (a, b) => CatValue::from_bool(func(&a, &b)),
+        };
+        self.push(result);
+        Ok(())
+    }
+
+    /// Pops `b` and `a` and pushes `a op b`. Two integers are combined with
+    /// `int_func`, otherwise both numbers are converted to floats and combined
+    /// with `float_func`. If either value is a stack, it's looped over.
+    fn run_arithmetic(
+        &mut self,
+        command: &'static str,
+        int_func: impl Fn(&Integer, &Integer) -> Result<CatValue, RuntimeError>,
+        float_func: impl Fn(f64, f64) -> f64,
+    ) -> Result<(), RuntimeError> {
+        let v1 = self.pop_res()?;
+        let v2 = self.pop_res()?;
+        let val = v1.auto_map(|v1| {
+            v2.auto_map_ref(|v2| match (v2, &v1) {
+                (VInteger(a), VInteger(b)) => int_func(a, b),
+                (a, b) => match (a.to_f64(), b.to_f64()) {
+                    (Some(a), Some(b)) => Ok(VFloat(float_func(a, b))),
+                    (None, _) => Err(wrong_type(command, "a number", a)),
+                    (_, None) => Err(wrong_type(command, "a number", b)),
+                },
+            })
+        })?;
+        self.push(val);
+        Ok(())
+    }
+
+    fn run_unary(
+        &mut self,
+        command: &'static str,
+        int_func: impl Fn(&Integer) -> Integer,
+        float_func: impl Fn(f64) -> f64,
+    ) -> Result<(), RuntimeError> {
+        self.replace_top(|val| {
+            val.auto_map_ref(|x| match x {
+                VInteger(v) => Ok(VInteger(int_func(v))),
+                VFloat(v) => Ok(VFloat(float_func(*v))),
+                x => Err(wrong_type(command, "a number", x)),
+            })
+        })
+    }
+
+    fn run_add(&mut self) -> Result<(), RuntimeError> {
         let v1 = self.pop_res()?;
         let v2 = self.pop_res()?;
         if let VStack(v1) = &v1 {
@@ -459,19 +1171,54 @@ impl Interpreter {
             }
         }
         let result = v1.auto_map(move |v1| match v1 {
-            VInteger(v1) => v2.auto_map_ref(|v2| match v2 {
-                VInteger(v2) => Ok(VInteger(v2 + v1)),
-                VString(v2) => Ok(VString(v2.clone() + &v1.to_string())),
-                x => Err(format!("Can't add int and {:?}", x)),
-            }),
             VString(v1) => v2.auto_map_ref(|v2| match v2 {
-                VInteger(v2) => Ok(VString(v2.to_string() + &v1)),
+                VInteger(_) | VFloat(_) => Ok(VString(v2.stringify() + &v1)),
                 VString(v2) => Ok(VString(v2.clone() + &v1)),
-                x => Err(format!("Can't add string and {:?}", x)),
+                x => Err(wrong_type("Add", "a number or a string", x)),
+            }),
+            v1 => v2.auto_map_ref(|v2| match (v2, &v1) {
+                (VInteger(a), VInteger(b)) => Ok(VInteger(a.add(b))),
+                (VString(a), b) if b.to_f64().is_some() => Ok(VString(a.clone() + &b.stringify())),
+                (a, b) => match (a.to_f64(), b.to_f64()) {
+                    (Some(a), Some(b)) => Ok(VFloat(a + b)),
+                    (None, _) => Err(wrong_type("Add", "a number or a string", a)),
+                    (_, None) => Err(wrong_type("Add", "a number or a string", b)),
+                },
             }),
-            x => Err(format!("Can't add {:?}", x)),
         })?;
         self.push(result);
         Ok(())
     }
 }
+
+/// A copy of the memory accounting state, for checking allocations inside
+/// closures that can't borrow the interpreter.
+#[derive(Clone, Copy)]
+struct MemoryBudget {
+    used: usize,
+    limit: Option<usize>,
+}
+
+impl MemoryBudget {
+    /// Fails if `size` more bytes would exceed the limit.
+    fn reserve(self, size: usize) -> Result<(), RuntimeError> {
+        match self.limit {
+            Some(limit) if self.used.saturating_add(size) > limit => {
+                Err(RuntimeError::MemoryLimitExceeded(limit))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn wrong_type(command: &'static str, expected: &'static str, found: &CatValue) -> RuntimeError {
+    RuntimeError::WrongType {
+        command,
+        expected,
+        found: found.debug_stringify(),
+    }
+}
+
+fn io_error(e: io::Error) -> RuntimeError {
+    RuntimeError::Io(e.to_string())
+}
@@ -0,0 +1,159 @@
+//! Checks a program against test cases, like online golf judges do.
+//!
+//! A case file lists the stdin of each case and the output it should
+//! produce, separated by a line with `---`. Cases are separated by a line
+//! with `===`:
+//!
+//! ```text
+//! 21
+//! ---
+//! 42
+//! ===
+//! 5
+//! ---
+//! 10
+//! ```
+//!
+//! Each line of input and output ends with a newline. Trailing newlines are
+//! ignored when comparing the output.
+
+use spec::Command;
+use std::time::{Duration, Instant};
+use {parse, run_commands, KatError, Limits, Output};
+
+/// A stdin and the output expected for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TestCase {
+    /// The line the case starts on in the case file, starting from 1.
+    pub line: usize,
+    pub input: String,
+    pub expected: String,
+}
+
+/// What running a program on a test case produced.
+#[derive(Clone, Debug)]
+pub struct CaseResult {
+    /// What the program printed, including the implicitly printed top of
+    /// the stack.
+    pub output: String,
+    pub error: Option<KatError>,
+    pub elapsed: Duration,
+}
+
+impl CaseResult {
+    pub fn passed(&self, case: &TestCase) -> bool {
+        self.error.is_none()
+            && self.output.trim_end_matches('\n') == case.expected.trim_end_matches('\n')
+    }
+}
+
+/// Reads the cases of a case file.
+pub fn parse_cases(text: &str) -> Result<Vec<TestCase>, String> {
+    let mut cases = Vec::new();
+    let mut line = 1;
+    for chunk in split_lines(text, "===") {
+        if chunk.trim().is_empty() {
+            line += chunk.lines().count() + 1;
+            continue;
+        }
+        let mut parts = split_lines(&chunk, "---");
+        if parts.len() != 2 {
+            return Err(format!(
+                "line {}: expected the input and the output separated by ---",
+                line
+            ));
+        }
+        let expected = parts.pop().unwrap();
+        let input = parts.pop().unwrap();
+        cases.push(TestCase {
+            line,
+            input,
+            expected,
+        });
+        line += chunk.lines().count() + 1;
+    }
+    Ok(cases)
+}
+
+/// Splits text at lines that consist of `separator`. Every line of the
+/// pieces ends with a newline.
+fn split_lines(text: &str, separator: &str) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    for line in text.lines() {
+        if line.trim_end() == separator {
+            pieces.push(String::new());
+        } else {
+            let piece = pieces.last_mut().unwrap();
+            piece.push_str(line);
+            piece.push('\n');
+        }
+    }
+    pieces
+}
+
+/// Runs a program on a test case. Each case gets a fresh interpreter.
+pub fn run_case(code: &str, case: &TestCase, limits: &Limits) -> CaseResult {
+    let start = Instant::now();
+    let result = parse(code).and_then(|commands| run_commands(&commands, &case.input, limits));
+    case_result(result, start.elapsed())
+}
+
+/// Runs parsed commands on a test case, like `run_case`.
+pub fn run_commands_case(commands: &[Command], case: &TestCase, limits: &Limits) -> CaseResult {
+    let start = Instant::now();
+    let result = run_commands(commands, &case.input, limits);
+    case_result(result, start.elapsed())
+}
+
+fn case_result(result: Result<Output, KatError>, elapsed: Duration) -> CaseResult {
+    match result {
+        Ok(mut result) => {
+            // The command line prints the top of the stack at the end.
+            if let Some(top) = result.stack.pop() {
+                result.output.push_str(&top.stringify());
+                result.output.push('\n');
+            }
+            CaseResult {
+                output: result.output,
+                error: None,
+                elapsed,
+            }
+        }
+        Err(e) => CaseResult {
+            output: String::new(),
+            error: Some(e),
+            elapsed,
+        },
+    }
+}
+
+/// Compares the expected and actual output line by line. Lines only in the
+/// expected output are marked with `-`, lines only in the actual output
+/// with `+`.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.trim_end_matches('\n').split('\n').collect();
+    let actual: Vec<&str> = actual.trim_end_matches('\n').split('\n').collect();
+    let mut out = String::new();
+    for i in 0..expected.len().max(actual.len()) {
+        match (expected.get(i), actual.get(i)) {
+            (Some(e), Some(a)) if e == a => {
+                out.push_str("  ");
+                out.push_str(e);
+                out.push('\n');
+            }
+            (e, a) => {
+                if let Some(e) = e {
+                    out.push_str("- ");
+                    out.push_str(e);
+                    out.push('\n');
+                }
+                if let Some(a) = a {
+                    out.push_str("+ ");
+                    out.push_str(a);
+                    out.push('\n');
+                }
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,112 @@
+//! Kat is a simple concatenative golf language. This crate contains the
+//! parser and the interpreter, and can be embedded without the command line
+//! interface by disabling the default `cli` feature.
+//!
+//! The simplest way to run a program is `run`:
+//!
+//! ```
+//! use katlang::{run, Limits};
+//!
+//! let result = run("RI2*W", "21\n", &Limits::default()).unwrap();
+//! assert_eq!(result.output, "42\n");
+//! ```
+//!
+//! For more control, parse with `parse` and execute the commands with an
+//! `Interpreter`. Host-provided commands can be added with `Natives`:
+//!
+//! ```
+//! use katlang::{Interpreter, Natives, Parser};
+//! use katlang::interpreter::CatValue;
+//!
+//! let mut natives = Natives::new();
+//! natives.register("dbl", |interpreter: &mut Interpreter| {
+//!     let value = interpreter.pop_res()?;
+//!     interpreter.push(CatValue::VStack(vec![value.clone(), value]));
+//!     Ok(())
+//! });
+//! let mut parser = Parser::with_natives(natives);
+//! parser.parse("1dbl").unwrap();
+//! let mut interpreter = Interpreter::new(false);
+//! interpreter.execute(parser.commands.iter()).unwrap();
+//! assert_eq!(interpreter.main_stack[0].debug_stringify(), "[1 1]");
+//! ```
+
+extern crate itertools;
+extern crate num_bigint;
+extern crate num_integer;
+extern crate num_traits;
+#[cfg(feature = "term")]
+extern crate termion;
+
+pub mod builtins;
+pub mod codepage;
+pub mod doctest;
+pub mod emit;
+pub mod error;
+pub mod explain;
+pub mod golf;
+pub mod integer;
+pub mod interpreter;
+pub mod judge;
+pub mod native;
+pub mod parser;
+pub mod spec;
+#[cfg(feature = "term")]
+pub mod term;
+
+pub use error::KatError;
+pub use interpreter::{CapturedOutput, CatValue, Interpreter, Limits};
+pub use native::{NativeCommand, Natives};
+pub use parser::Parser;
+pub use spec::{CatCommand, Command};
+
+use std::io;
+
+/// What a program left behind after running with `run`.
+#[derive(Clone, Debug)]
+pub struct Output {
+    /// The main stack, with the top value last.
+    pub stack: Vec<CatValue>,
+    /// Everything the program wrote.
+    pub output: String,
+}
+
+/// Parses a program into a list of commands.
+pub fn parse(code: &str) -> Result<Vec<Command>, KatError> {
+    let mut parser = Parser::new();
+    parser.parse(code)?;
+    Ok(parser.commands)
+}
+
+/// Parses a program written in the verbose dialect, see
+/// `Parser::parse_verbose`.
+pub fn parse_verbose(code: &str) -> Result<Vec<Command>, KatError> {
+    let mut parser = Parser::new();
+    parser.parse_verbose(code)?;
+    Ok(parser.commands)
+}
+
+/// Parses and runs a program, with `input` as its stdin.
+pub fn run(code: &str, input: &str, limits: &Limits) -> Result<Output, KatError> {
+    run_commands(&parse(code)?, input, limits)
+}
+
+/// Runs parsed commands, like `run` does.
+pub fn run_commands(
+    commands: &[Command],
+    input: &str,
+    limits: &Limits,
+) -> Result<Output, KatError> {
+    let output = CapturedOutput::new();
+    let mut interpreter = Interpreter::with_io(
+        false,
+        Box::new(io::Cursor::new(input.as_bytes().to_vec())),
+        Box::new(output.clone()),
+    );
+    interpreter.set_limits(limits);
+    interpreter.execute(commands.iter())?;
+    Ok(Output {
+        stack: interpreter.main_stack,
+        output: output.contents(),
+    })
+}
@@ -1,23 +1,25 @@
 extern crate clap;
-extern crate itertools;
-extern crate termion;
+extern crate katlang;
 
-mod interpreter;
-mod parser;
-mod spec;
-mod term;
-
-use clap::{App, Arg};
-use interpreter::{CatValue::VStack, ExecFrame, Interpreter};
-use parser::Parser;
-use std::time::Instant;
-use term::run_term;
+use clap::{App, AppSettings, Arg, SubCommand};
+use katlang::interpreter::{
+    CatValue::{VStack, VString},
+    ExecFrame,
+};
+use katlang::judge::{self, TestCase};
+use katlang::term::run_term;
+use katlang::{builtins, codepage, doctest, emit, explain, golf};
+use katlang::{Command, Interpreter, KatError, Limits, Natives};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
 
 fn print_frame(frame: ExecFrame, depth: usize) {
     println!(
-        ">  {: <40} {} | Stack before: {: <40} | Stack after: {}",
+        ">  {: <40} | Stack before: {: <40} | Stack after: {}",
         format!("{}{:?}", " ".repeat(depth * 2), frame.command),
-        if frame.reading { "(read)" } else { "      " },
         {
             let v = VStack(frame.stack_before).debug_stringify();
             if v.len() > 37 {
@@ -43,27 +45,45 @@ fn print_frame(frame: ExecFrame, depth: usize) {
         .for_each(|f| print_frame(f, depth + 1));
 }
 
-fn run_snippet(code: &str, trace: bool) -> Result<(), String> {
+/// Settings for running a program from the command line.
+struct RunOptions {
+    trace: bool,
+    limits: Limits,
+}
+
+fn run_snippet(
+    commands: &[Command],
+    options: &RunOptions,
+    args: &[String],
+) -> Result<(), KatError> {
+    let trace = options.trace;
     let now = Instant::now();
-    let mut parser = Parser::new();
-    parser.parse(code)?;
-    println!("{:?}", parser.commands.clone());
+    println!("{:?}", commands);
     let mut interpreter = Interpreter::new(trace);
+    interpreter.set_limits(&options.limits);
+    for arg in args {
+        interpreter.push(VString(arg.clone()));
+    }
     if trace {
-        for command in parser.commands {
-            interpreter.execute_single(&command)?;
+        for command in commands {
+            interpreter.execute_single(command)?;
             for frame in interpreter.exec_frames.drain(0..) {
                 print_frame(frame, 0);
             }
         }
     } else {
-        interpreter.execute(parser.commands.iter())?;
+        interpreter.execute(commands.iter())?;
     }
-    interpreter.pop().map(|v| {
+    if let Some(v) = interpreter.pop() {
         println!("{}", v.stringify());
-    });
+    }
     let elapsed = now.elapsed();
-    println!("{} s {} µs", elapsed.as_secs(), elapsed.subsec_micros());
+    println!(
+        "{} s {} µs, {} steps",
+        elapsed.as_secs(),
+        elapsed.subsec_micros(),
+        interpreter.steps()
+    );
     Ok(())
 }
 
@@ -72,6 +92,7 @@ fn run() -> Result<(), String> {
         .version("1.0")
         .author("Jani Mustonen <janijohannes@kapsi.fi>")
         .about("A simple concatenative golf language")
+        .setting(AppSettings::TrailingVarArg)
         .arg(
             Arg::with_name("code")
                 .short("c")
@@ -85,27 +106,427 @@ fn run() -> Result<(), String> {
                 .short("i")
                 .help("Interactive edit mode"),
         )
+        .arg(
+            Arg::with_name("doc")
+                .long("doc")
+                .help("Prints the command reference as Markdown"),
+        )
+        .arg(
+            Arg::with_name("cases")
+                .long("cases")
+                .value_name("FILE")
+                .help("Runs the program on each test case in FILE and compares the output")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("explain")
+                .long("explain")
+                .help("Prints an explanation of the program instead of running it"),
+        )
+        .arg(
+            Arg::with_name("bytes")
+                .long("bytes")
+                .help("Prints the length of the program in the code page instead of running it"),
+        )
+        .arg(
+            Arg::with_name("encode").long("encode").help(
+                "Writes the program encoded in the code page to stdout instead of running it",
+            ),
+        )
+        .arg(
+            Arg::with_name("code-page")
+                .long("code-page")
+                .help("Reads the program file as code page bytes instead of UTF-8"),
+        )
+        .arg(
+            Arg::with_name("verbose-source")
+                .long("verbose-source")
+                .help("Reads the program in the verbose dialect, where commands are words"),
+        )
+        .arg(
+            Arg::with_name("terse")
+                .long("terse")
+                .help("Prints the program in the terse dialect instead of running it"),
+        )
         .arg(
             Arg::with_name("trace")
                 .short("t")
                 .help("Traces the entire execution"),
         )
+        .arg(
+            Arg::with_name("max-steps")
+                .long("max-steps")
+                .value_name("STEPS")
+                .help("Aborts after executing this many commands")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Aborts if the program runs longer than this")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-memory")
+                .long("max-memory")
+                .value_name("BYTES")
+                .help("Aborts if the values on the stacks and in variables grow larger than this")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("max-depth")
+                .long("max-depth")
+                .value_name("DEPTH")
+                .help("Aborts if blocks are nested deeper than this while executing")
+                .default_value("10000")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("file")
+                .value_name("FILE")
+                .help("Executes a program file, or the program read from stdin if FILE is -")
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("args")
+                .value_name("ARGS")
+                .help("Arguments pushed to the stack as strings before execution")
+                .index(2)
+                .multiple(true)
+                .allow_hyphen_values(true),
+        )
+        .subcommand(
+            SubCommand::with_name("test-docs")
+                .about("Runs the katlang snippets in Markdown files and checks their results")
+                .arg(
+                    Arg::with_name("files")
+                        .value_name("FILES")
+                        .help("The files to check, README.md, spec.md and examples.md by default")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("golf")
+                .about("Shrinks a program, keeping each rewrite only if the test cases still pass")
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .help("The program file, or - to read it from stdin")
+                        .required(true),
+                )
+                .arg(
+                    Arg::with_name("cases")
+                        .long("cases")
+                        .value_name("FILE")
+                        .help("The test cases the golfed program has to pass")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
         .get_matches();
+    if let Some(matches) = matches.subcommand_matches("test-docs") {
+        let files = matches
+            .values_of("files")
+            .map(|files| files.collect())
+            .unwrap_or_else(|| DOC_FILES.to_vec());
+        return test_docs(&files);
+    }
     let code = matches.value_of("code");
-    let trace = matches.is_present("trace");
+    let file = matches.value_of("file");
+    let mut args: Vec<String> = matches
+        .values_of("args")
+        .map(|args| args.map(|arg| arg.to_owned()).collect())
+        .unwrap_or_default();
     let interactive = matches.is_present("interactive");
+    let verbose = matches.is_present("verbose-source");
+    let options = RunOptions {
+        trace: matches.is_present("trace"),
+        limits: Limits {
+            max_steps: matches
+                .value_of("max-steps")
+                .map(|v| v.parse().map_err(|_| format!("Invalid step limit: {}", v)))
+                .transpose()?,
+            timeout: matches
+                .value_of("timeout")
+                .map(|v| match v.parse::<f64>() {
+                    Ok(secs) if secs >= 0.0 && secs.is_finite() => {
+                        Ok(Duration::from_secs_f64(secs))
+                    }
+                    _ => Err(format!("Invalid timeout: {}", v)),
+                })
+                .transpose()?,
+            max_memory: matches
+                .value_of("max-memory")
+                .map(|v| {
+                    v.parse()
+                        .map_err(|_| format!("Invalid memory limit: {}", v))
+                })
+                .transpose()?,
+            max_depth: matches
+                .value_of("max-depth")
+                .map(|v| v.parse().map_err(|_| format!("Invalid depth limit: {}", v)))
+                .transpose()?,
+        },
+    };
+
+    if let Some(golf_matches) = matches.subcommand_matches("golf") {
+        let code = read_program(
+            golf_matches.value_of("file").unwrap(),
+            matches.is_present("code-page"),
+        )?;
+        let commands = parse_program(&code, verbose)?;
+        let cases = read_cases(golf_matches.value_of("cases").unwrap())?;
+        return golf_program(&code, commands, verbose, &cases, &options.limits);
+    }
+
+    if matches.is_present("doc") {
+        print!("{}", builtins::reference());
+    } else if interactive {
+        run_term(&options.limits, &Natives::new())?;
+    } else {
+        let code = match (code, file) {
+            (Some(code), _) => {
+                // With -c, every positional argument is a program argument.
+                if let Some(file) = file {
+                    args.insert(0, file.to_owned());
+                }
+                code.to_owned()
+            }
+            (None, Some(file)) => read_program(file, matches.is_present("code-page"))?,
+            (None, None) => return Ok(()),
+        };
+        if matches.is_present("terse") {
+            let commands = parse_program(&code, verbose)?;
+            let terse = emit::emit(&commands).map_err(|e| e.render(&code))?;
+            println!("{}", terse);
+        } else if matches.is_present("explain") {
+            // Explanations are laid out under terse source.
+            let code = if verbose { to_terse(&code)? } else { code };
+            let commands = parse_program(&code, false)?;
+            print!("{}", explain::explain(&code, &commands));
+        } else if matches.is_present("bytes") || matches.is_present("encode") {
+            // Sizes are measured from terse source.
+            let code = if verbose { to_terse(&code)? } else { code };
+            if matches.is_present("bytes") {
+                match codepage::encode(&code) {
+                    Ok(bytes) => println!("{} bytes", bytes.len()),
+                    Err(e) => println!("{} bytes in UTF-8, {}", code.len(), e),
+                }
+            } else {
+                let bytes = codepage::encode(&code).map_err(|e| e.to_string())?;
+                io::stdout()
+                    .write_all(&bytes)
+                    .map_err(|e| format!("Could not write stdout: {}", e))?;
+            }
+        } else if let Some(cases) = matches.value_of("cases") {
+            let commands = parse_program(&code, verbose)?;
+            let cases = read_cases(cases)?;
+            // Verbose programs are scored by their terse form, if they
+            // have one.
+            let terse = if verbose {
+                emit::emit(&commands).ok()
+            } else {
+                Some(code.clone())
+            };
+            test_cases(&code, &commands, terse.as_deref(), &cases, &options.limits);
+        } else {
+            let commands = parse_program(&code, verbose)?;
+            run_snippet(&commands, &options, &args).map_err(|e| e.render(&code))?;
+        }
+    }
+    Ok(())
+}
+
+/// Runs a program on each test case and prints a table of the results,
+/// followed by the details of the failed cases. The size is given by the
+/// terse source, if there is one.
+fn test_cases(
+    code: &str,
+    commands: &[Command],
+    terse: Option<&str>,
+    cases: &[TestCase],
+    limits: &Limits,
+) {
+    let results: Vec<_> = cases
+        .iter()
+        .map(|case| judge::run_commands_case(commands, case, limits))
+        .collect();
+    println!("{:<6} {:<6} {:<6} {:>10}", "Case", "Line", "Result", "Time");
+    for (i, (case, result)) in cases.iter().zip(&results).enumerate() {
+        let status = if result.passed(case) {
+            "pass"
+        } else if result.error.is_some() {
+            "error"
+        } else {
+            "FAIL"
+        };
+        println!(
+            "{:<6} {:<6} {:<6} {:>7} µs",
+            i + 1,
+            case.line,
+            status,
+            result.elapsed.as_micros()
+        );
+    }
+    for (i, (case, result)) in cases.iter().zip(&results).enumerate() {
+        if result.passed(case) {
+            continue;
+        }
+        println!();
+        println!("Case {} (line {}):", i + 1, case.line);
+        match &result.error {
+            Some(e) => println!("{}", e.render(code)),
+            None => print!("{}", judge::diff(&case.expected, &result.output)),
+        }
+    }
+    let passed = cases
+        .iter()
+        .zip(&results)
+        .filter(|(case, result)| result.passed(case))
+        .count();
+    println!();
+    match terse {
+        Some(terse) => println!(
+            "{}/{} passed, {} bytes",
+            passed,
+            cases.len(),
+            codepage::score(terse)
+        ),
+        None => println!("{}/{} passed", passed, cases.len()),
+    }
+    if passed < cases.len() {
+        process::exit(1);
+    }
+}
+
+/// Parses a program, in the verbose dialect if `verbose` is set.
+fn parse_program(code: &str, verbose: bool) -> Result<Vec<Command>, String> {
+    let commands = if verbose {
+        katlang::parse_verbose(code)
+    } else {
+        katlang::parse(code)
+    };
+    commands.map_err(|e| e.render(code))
+}
+
+/// Translates verbose source to terse source.
+fn to_terse(code: &str) -> Result<String, String> {
+    let commands = katlang::parse_verbose(code).map_err(|e| e.render(code))?;
+    emit::emit(&commands).map_err(|e| e.render(code))
+}
+
+fn read_cases(path: &str) -> Result<Vec<TestCase>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    judge::parse_cases(&text).map_err(|e| format!("{}: {}", path, e))
+}
 
-    if interactive {
-        run_term()?;
-    } else if let Some(code) = code {
-        run_snippet(code, trace)?;
+/// Golfs a program and prints it. The applied rewrites are listed on stderr,
+/// so that stdout only has the program. Verbose programs are golfed from
+/// their commands, so the golfed program is always terse.
+fn golf_program(
+    code: &str,
+    commands: Vec<Command>,
+    verbose: bool,
+    cases: &[TestCase],
+    limits: &Limits,
+) -> Result<(), String> {
+    let passes = |code: &str| {
+        cases
+            .iter()
+            .all(|case| judge::run_case(code, case, limits).passed(case))
+    };
+    let original_passes = cases
+        .iter()
+        .all(|case| judge::run_commands_case(&commands, case, limits).passed(case));
+    if !original_passes {
+        return Err("The program doesn't pass the test cases".to_owned());
+    }
+    let golfed = if verbose {
+        golf::golf_commands(commands, passes)
+    } else {
+        golf::golf(code, passes)
+    }
+    .map_err(|e| e.render(code))?;
+    for rewrite in &golfed.rewrites {
+        eprintln!("{}", rewrite);
     }
+    eprintln!(
+        "{} -> {} bytes",
+        codepage::score(code),
+        codepage::score(&golfed.code)
+    );
+    println!("{}", golfed.code);
     Ok(())
 }
 
+/// The documents checked by `test-docs` when no files are given.
+const DOC_FILES: [&str; 3] = ["README.md", "spec.md", "examples.md"];
+
+/// Runs the snippets in the given Markdown files, printing each failure.
+fn test_docs(files: &[&str]) -> Result<(), String> {
+    let mut total = 0;
+    let mut failed = 0;
+    for file in files {
+        let text =
+            fs::read_to_string(file).map_err(|e| format!("Could not read {}: {}", file, e))?;
+        let snippets = doctest::snippets(&text).map_err(|e| format!("{}: {}", file, e))?;
+        for snippet in snippets {
+            total += 1;
+            if let Err(e) = snippet.check() {
+                failed += 1;
+                println!("{}:{}: {}\n  {}", file, snippet.line, e, snippet.code);
+            }
+        }
+    }
+    println!("{} snippets, {} failed", total, failed);
+    if failed > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Reads a program from a file, or from stdin if the path is `-`. The file is
+/// decoded from the code page if `code_page` is set and from UTF-8
+/// otherwise. A single trailing newline is removed, since it would otherwise
+/// be parsed as a string literal.
+fn read_program(path: &str, code_page: bool) -> Result<String, String> {
+    let mut bytes = Vec::new();
+    if path == "-" {
+        io::stdin()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Could not read stdin: {}", e))?;
+    } else {
+        bytes = fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+    }
+    let mut code = if code_page {
+        codepage::decode(&bytes)
+    } else {
+        String::from_utf8(bytes).map_err(|_| format!("{} is not valid UTF-8", path))?
+    };
+    if code.ends_with('\n') {
+        code.pop();
+        if code.ends_with('\r') {
+            code.pop();
+        }
+    }
+    Ok(code)
+}
+
+/// Nested blocks are executed recursively, so the interpreter runs on a
+/// thread with a large stack. The default `--max-depth` fits in it even in
+/// debug builds. Only the pages that are actually used get allocated.
+const STACK_SIZE: usize = 1 << 30;
+
 fn main() {
-    match run() {
-        Ok(()) => {}
-        Err(e) => println!("Error: {}", e),
+    let result = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .expect("Could not start the interpreter thread")
+        .join();
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => println!("Error: {}", e),
+        Err(_) => process::exit(101),
     }
 }
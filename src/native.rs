@@ -0,0 +1,108 @@
+use error::RuntimeError;
+use interpreter::Interpreter;
+use std::fmt;
+use std::rc::Rc;
+
+/// The implementation of a native command. It operates directly on the
+/// interpreter's stacks.
+pub type NativeFn = dyn Fn(&mut Interpreter) -> Result<(), RuntimeError>;
+
+/// A command implemented by the embedding program.
+#[derive(Clone)]
+pub struct NativeCommand {
+    name: String,
+    func: Rc<NativeFn>,
+}
+
+impl NativeCommand {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn call(&self, interpreter: &mut Interpreter) -> Result<(), RuntimeError> {
+        (self.func)(interpreter)
+    }
+}
+
+/// Native commands are identified by their name.
+impl PartialEq for NativeCommand {
+    fn eq(&self, other: &NativeCommand) -> bool {
+        self.name == other.name
+    }
+}
+
+impl fmt::Debug for NativeCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.name.fmt(f)
+    }
+}
+
+/// Characters that can't start the name of a native command, since they
+/// start or end literals, blocks or comments.
+const RESERVED: &[char] = &[
+    '"', '\'', '«', '»', '¯', '[', ']', '(', ')', '{', '}', '$', '`', '¶',
+];
+
+/// A set of native commands to make available to the parser.
+#[derive(Clone, Debug, Default)]
+pub struct Natives {
+    commands: Vec<NativeCommand>,
+}
+
+impl Natives {
+    pub fn new() -> Natives {
+        Natives::default()
+    }
+
+    /// Registers a command under `name`, which can be a single character or
+    /// longer. Natives take precedence over builtins and variables, and the
+    /// longest matching name wins. Registering a name again replaces the
+    /// previous command.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is empty, contains whitespace or starts with a digit
+    /// or a character that starts a literal, a block or a comment.
+    pub fn register(
+        &mut self,
+        name: &str,
+        func: impl Fn(&mut Interpreter) -> Result<(), RuntimeError> + 'static,
+    ) {
+        let first = name
+            .chars()
+            .next()
+            .expect("Native command names can't be empty");
+        assert!(
+            !first.is_ascii_digit() && !RESERVED.contains(&first),
+            "Native command names can't start with {:?}",
+            first
+        );
+        assert!(
+            !name.chars().any(char::is_whitespace),
+            "Native command names can't contain whitespace"
+        );
+        self.commands.retain(|command| command.name != name);
+        self.commands.push(NativeCommand {
+            name: name.to_owned(),
+            func: Rc::new(func),
+        });
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &NativeCommand> {
+        self.commands.iter()
+    }
+
+    /// Finds the command with the longest name that `text` starts with.
+    pub fn longest_match(
+        &self,
+        text: impl Iterator<Item = char> + Clone,
+    ) -> Option<&NativeCommand> {
+        self.commands
+            .iter()
+            .filter(|command| {
+                let mut text = text.clone();
+                command.name.chars().all(|c| text.next() == Some(c))
+            })
+            .max_by_key(|command| command.name.chars().count())
+    }
+}
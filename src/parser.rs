@@ -1,5 +1,10 @@
-use spec::CatCommand;
+use builtins::{self, ParseRule};
+use error::{KatError, ParseError};
+use native::Natives;
+use spec::{CatCommand, Command, Span};
 use std::iter::Peekable;
+use std::mem;
+use std::str::Chars;
 
 enum ReadResult {
     Ok,
@@ -7,11 +12,154 @@ enum ReadResult {
     Done,
 }
 
-#[derive(Debug)]
+/// Reads the next command of a dialect.
+type ReadFn = fn(&mut Parser, &mut Cursor) -> Result<ReadResult, KatError>;
+
+/// A character iterator that keeps track of its position in the source.
+struct Cursor<'a> {
+    chars: Peekable<Chars<'a>>,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(text: &'a str) -> Cursor<'a> {
+        Cursor {
+            chars: text.chars().peekable(),
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.chars.clone().nth(1)
+    }
+
+    fn here(&self) -> Span {
+        Span {
+            offset: self.offset,
+            line: self.line,
+            column: self.column,
+            len: 0,
+        }
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            len: self.offset - start.offset,
+            ..start
+        }
+    }
+}
+
+impl<'a> Iterator for Cursor<'a> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.offset += 1;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Parser {
-    pub commands: Vec<CatCommand>,
+    pub commands: Vec<Command>,
     pub known_variables: Vec<char>,
+    hoisted: Vec<Command>,
     whitespace_needed: bool,
+    natives: Natives,
+    /// How many blocks and quotes enclose the command being read.
+    depth: usize,
+}
+
+const CLOSERS: [char; 3] = [')', ']', '}'];
+
+/// How deeply blocks, lists and quotes can be nested. Parsing recurses on
+/// the native stack, so this keeps hostile source from overflowing it.
+pub const MAX_NESTING: usize = 128;
+
+/// Starts a comment that runs to the end of the line, newline included.
+pub const COMMENT: char = '¶';
+
+/// Skips a comment, if one starts here.
+fn skip_comment(chars: &mut Cursor) -> bool {
+    if chars.peek() != Some(&COMMENT) {
+        return false;
+    }
+    for c in chars {
+        if c == '\n' {
+            break;
+        }
+    }
+    true
+}
+
+/// Skips whitespace and comments.
+fn skip_space(chars: &mut Cursor) {
+    while skip_comment(chars) || chars.peek().is_some_and(|c| c.is_whitespace()) {
+        if chars.peek().is_some_and(|c| c.is_whitespace()) {
+            chars.next();
+        }
+    }
+}
+
+/// Reads the rest of an escape sequence in a string, whose backslash
+/// starts at `start`: `\n`, `\t`, `\r`, `\\`, `\"` or `\u{...}` with the
+/// hexadecimal code point of any character.
+fn read_escape(chars: &mut Cursor, start: Span) -> Result<char, KatError> {
+    let mut escape = String::from("\\");
+    let c = chars
+        .next()
+        .expect("Escapes are only read before a character");
+    escape.push(c);
+    let unescaped = match c {
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'r' => Some('\r'),
+        '\\' | '"' => Some(c),
+        'u' if chars.peek() == Some(&'{') => {
+            chars.next();
+            escape.push('{');
+            let mut hex = String::new();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_hexdigit() || hex.len() == 6 {
+                    break;
+                }
+                hex.push(c);
+                chars.next();
+            }
+            escape.push_str(&hex);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                escape.push('}');
+                u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+            } else {
+                None
+            }
+        }
+        _ => None,
+    };
+    unescaped
+        .ok_or_else(|| KatError::Parse(ParseError::InvalidEscape(escape), chars.span_from(start)))
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
 }
 
 impl Parser {
@@ -19,310 +167,557 @@ impl Parser {
         Parser {
             commands: vec![],
             known_variables: vec![],
+            hoisted: vec![],
             whitespace_needed: false,
+            natives: Natives::new(),
+            depth: 0,
+        }
+    }
+
+    /// Creates a parser that also recognizes the given native commands.
+    pub fn with_natives(natives: Natives) -> Parser {
+        Parser {
+            natives,
+            ..Parser::new()
         }
     }
 
-    pub fn parse(&mut self, text: &str) -> Result<(), String> {
-        let mut chars = text.chars().peekable();
+    pub fn parse(&mut self, text: &str) -> Result<(), KatError> {
+        self.parse_with(text, Parser::read_one)?;
+        let hoisted = mem::take(&mut self.hoisted);
+        self.commands.splice(0..0, hoisted);
+        Ok(())
+    }
+
+    /// Parses source in the verbose dialect. Commands are written as words,
+    /// like `dup` or `swap`, and whitespace only separates them. A command
+    /// that takes an implicit block is followed by the block in braces, as
+    /// in `map { 1 add }`, `set v` and `get v` assign and fetch variables and
+    /// `call v` executes one. The commands are the same the equivalent terse
+    /// source gives.
+    pub fn parse_verbose(&mut self, text: &str) -> Result<(), KatError> {
+        self.parse_with(text, Parser::read_verbose_one)
+    }
+
+    fn parse_with(&mut self, text: &str, read: ReadFn) -> Result<(), KatError> {
+        let mut chars = Cursor::new(text);
         loop {
-            match self.read_one(&mut chars, &[]) {
+            match read(self, &mut chars)? {
                 ReadResult::Ok => {}
-                ReadResult::NoMatch(_) => {
-                    return match chars.peek() {
-                        Some(c) => Err(format!("Unexpected character: {}", c)),
-                        None => Err(format!("Unexpected EOF")),
-                    }
+                ReadResult::NoMatch(c) => {
+                    let err = if CLOSERS.contains(&c) {
+                        ParseError::UnmatchedClose(c)
+                    } else {
+                        ParseError::UnexpectedCharacter(c)
+                    };
+                    return Err(KatError::Parse(
+                        err,
+                        Span {
+                            len: 1,
+                            ..chars.here()
+                        },
+                    ));
                 }
                 ReadResult::Done => return Ok(()),
             }
         }
     }
 
-    fn read_one<I: Iterator<Item = char>>(
-        &mut self,
-        chars: &mut Peekable<I>,
-        excluded: &[char],
-    ) -> ReadResult {
+    fn push(&mut self, kind: CatCommand, span: Span) {
+        self.commands.push(Command::new(kind, span));
+    }
+
+    /// Reads something nested one level deeper, failing at `MAX_NESTING`.
+    /// `open` is the span of the glyph that started the nesting.
+    fn nested<T, F>(&mut self, chars: &mut Cursor, open: Span, read: F) -> Result<T, KatError>
+    where
+        F: FnOnce(&mut Parser, &mut Cursor) -> Result<T, KatError>,
+    {
+        if self.depth >= MAX_NESTING {
+            return Err(KatError::Parse(
+                ParseError::TooDeeplyNested(MAX_NESTING),
+                open,
+            ));
+        }
+        self.depth += 1;
+        let result = read(self, chars);
+        self.depth -= 1;
+        result
+    }
+
+    fn read_one(&mut self, chars: &mut Cursor) -> Result<ReadResult, KatError> {
         let c = if let Some(c) = chars.peek() {
             *c
         } else {
-            return ReadResult::Done;
+            return Ok(ReadResult::Done);
         };
-        if excluded.contains(&c) {
-            return ReadResult::NoMatch(c);
+        if CLOSERS.contains(&c) {
+            return Ok(ReadResult::NoMatch(c));
+        }
+        // A comment is skipped as if it wasn't there.
+        if skip_comment(chars) {
+            return Ok(ReadResult::Ok);
         }
         if c.is_whitespace() {
+            let start = chars.here();
             chars.next();
             if !self.whitespace_needed {
-                self.commands.push(CatCommand::CreateString(c.to_string()));
+                self.push(
+                    CatCommand::CreateString(c.to_string()),
+                    chars.span_from(start),
+                );
             }
             self.whitespace_needed = false;
-            return ReadResult::Ok;
+            return Ok(ReadResult::Ok);
         }
         self.whitespace_needed = false;
 
         if c == '"' {
-            chars.next();
-            self.read_string(chars);
+            self.read_string(chars)?;
+        } else if c == '«' {
+            self.read_raw_string(chars)?;
         } else if c == '\'' {
-            chars.next();
-            self.read_char(chars);
-        } else if c.is_digit(10) {
-            self.read_digit(chars)
-        } else if self.read_command(chars) {
+            self.read_char(chars)?;
+        } else if c.is_ascii_digit()
+            || (c == '¯' && chars.peek_second().is_some_and(|c| c.is_ascii_digit()))
+        {
+            self.read_digit(chars);
+        } else if self.read_command(chars)? {
         } else if c == '{' {
-            self.read_named_block(chars);
+            self.read_named_block(chars)?;
         } else if c == '$' {
-            return ReadResult::NoMatch(c);
+            return Ok(ReadResult::NoMatch(c));
         } else if self.known_variables.contains(&c) {
-            self.commands.push(CatCommand::PopVariable(c, true));
+            let start = chars.here();
             chars.next();
-        } else if self.read_pre_named_block(chars) {
+            self.push(CatCommand::PopVariable(c, true), chars.span_from(start));
         } else {
-            return ReadResult::NoMatch(c);
+            self.read_pre_named_block(chars)?;
         }
-        ReadResult::Ok
+        Ok(ReadResult::Ok)
     }
 
-    fn read_digit<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) {
-        let mut digits: Vec<i64> = vec![];
-        loop {
-            let c = if let Some(c) = chars.peek() {
-                *c
-            } else {
-                break;
-            };
-            if let Some(d) = c.to_digit(10) {
-                digits.push(d as i64);
+    /// Reads commands until a terminator (`$` or a closing bracket) or EOF,
+    /// without consuming the terminator. Returns the commands and the
+    /// terminator, which is `None` on EOF. `open` is the span of the glyph
+    /// that started the body.
+    fn read_body(
+        &mut self,
+        chars: &mut Cursor,
+        open: Span,
+        read: ReadFn,
+    ) -> Result<(Vec<Command>, Option<char>), KatError> {
+        let outer = mem::take(&mut self.commands);
+        let terminator = self.nested(chars, open, |parser, chars| loop {
+            match read(parser, chars)? {
+                ReadResult::Ok => {}
+                ReadResult::NoMatch(c) => return Ok(Some(c)),
+                ReadResult::Done => return Ok(None),
+            }
+        });
+        let body = mem::replace(&mut self.commands, outer);
+        Ok((body, terminator?))
+    }
+
+    /// Reads a bracketed block whose opening bracket has already been consumed.
+    fn read_bracketed(
+        &mut self,
+        chars: &mut Cursor,
+        open: Span,
+        close: char,
+        read: ReadFn,
+    ) -> Result<Vec<Command>, KatError> {
+        let (body, terminator) = self.read_body(chars, open, read)?;
+        let here = Span {
+            len: 1,
+            ..chars.here()
+        };
+        match terminator {
+            Some(c) if c == close => {
+                chars.next();
+                Ok(body)
+            }
+            Some(c) if CLOSERS.contains(&c) => {
+                Err(KatError::Parse(ParseError::MismatchedClose(close, c), here))
+            }
+            Some(c) => Err(KatError::Parse(ParseError::UnexpectedCharacter(c), here)),
+            None => Err(KatError::Parse(ParseError::UnclosedBlock(close), open)),
+        }
+    }
+
+    fn read_digit(&mut self, chars: &mut Cursor) {
+        let start = chars.here();
+        let mut digits = String::new();
+        if chars.peek() == Some(&'¯') {
+            chars.next();
+            digits.push('-');
+        }
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_digit() {
+                digits.push(c);
                 chars.next();
             } else {
                 self.whitespace_needed = true;
                 break;
             }
         }
-        let num = digits
-            .iter()
-            .enumerate()
-            .map(|(i, v)| 10i64.pow((digits.len() - i - 1) as u32) * v)
-            .sum();
-        self.commands.push(CatCommand::CreateInteger(num))
+        if chars.peek() == Some(&'.') && chars.peek_second().is_some_and(|c| c.is_ascii_digit()) {
+            digits.push('.');
+            chars.next();
+            while let Some(&c) = chars.peek() {
+                if !c.is_ascii_digit() {
+                    break;
+                }
+                digits.push(c);
+                chars.next();
+            }
+            let num = digits.parse().expect("Digits always form a valid float");
+            self.push(CatCommand::CreateFloat(num), chars.span_from(start));
+            return;
+        }
+        let num = digits.parse().expect("Digits always form a valid integer");
+        self.push(CatCommand::CreateInteger(num), chars.span_from(start))
     }
 
-    fn read_string<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) {
-        let mut buffer: Vec<char> = vec![];
+    fn read_string(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
+        chars.next();
+        let mut buffer = String::new();
         loop {
-            let mut c = if let Some(c) = chars.next() {
-                c
-            } else {
-                break;
-            };
-            if c == '\\' {
-                c = if let Some(c) = chars.next() {
-                    c
-                } else {
-                    break;
-                };
-            } else if c == '"' {
-                break;
+            let here = chars.here();
+            match chars.next() {
+                Some('"') => break,
+                // A backslash at EOF leaves the string unclosed.
+                Some('\\') if chars.peek().is_some() => buffer.push(read_escape(chars, here)?),
+                Some(c) => buffer.push(c),
+                None => {
+                    return Err(KatError::Parse(
+                        ParseError::UnclosedString('"'),
+                        Span { len: 1, ..start },
+                    ))
+                }
             }
-            buffer.push(c);
         }
-        self.commands
-            .push(CatCommand::CreateString(buffer.into_iter().collect()));
+        self.push(CatCommand::CreateString(buffer), chars.span_from(start));
+        Ok(())
     }
 
-    fn read_char<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) {
-        let c = if let Some(c) = chars.next() {
-            c
-        } else {
-            return;
+    /// Reads a raw string, which runs from `«` to the next `»` without any
+    /// escapes.
+    fn read_raw_string(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
+        chars.next();
+        let mut buffer = String::new();
+        loop {
+            match chars.next() {
+                Some('»') => break,
+                Some(c) => buffer.push(c),
+                None => {
+                    return Err(KatError::Parse(
+                        ParseError::UnclosedString('»'),
+                        Span { len: 1, ..start },
+                    ))
+                }
+            }
+        }
+        self.push(CatCommand::CreateString(buffer), chars.span_from(start));
+        Ok(())
+    }
+
+    fn read_char(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
+        chars.next();
+        let c = match chars.next() {
+            Some(c) => c,
+            None => return Err(KatError::Parse(ParseError::UnexpectedEof, chars.here())),
         };
-        self.commands.push(CatCommand::CreateString(c.to_string()));
+        self.push(
+            CatCommand::CreateString(c.to_string()),
+            chars.span_from(start),
+        );
+        Ok(())
     }
 
-    fn read_command<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) -> bool {
+    fn read_command(&mut self, chars: &mut Cursor) -> Result<bool, KatError> {
         let c = if let Some(c) = chars.peek() {
             *c
         } else {
-            return false;
+            return Ok(false);
         };
-        let mut no_next = false;
-        let cmd = match c {
-            '[' => CatCommand::StartBlock,
-            ']' => CatCommand::CloseBlock,
-            '(' => CatCommand::StartBlock,
-            ')' => CatCommand::CloseBlock,
-            '`' => {
-                no_next = true;
+        let start = chars.here();
+        if let Some(native) = self.natives.longest_match(chars.chars.clone()) {
+            let native = native.clone();
+            for _ in native.name().chars() {
                 chars.next();
-                self.read_command(chars);
-                let f = self.commands.pop().unwrap();
-                CatCommand::CreateCommand(Box::new(f))
             }
-            '+' => CatCommand::Add,
-            '*' => CatCommand::Multiply,
-            'R' => CatCommand::ReadLine,
-            'W' => CatCommand::WriteLine,
-            'w' => CatCommand::Write,
-            '&' => {
-                if !self.read_command_block(chars) {
-                    return false;
-                }
-                no_next = true;
-                CatCommand::Map
+            self.push(CatCommand::Native(native), chars.span_from(start));
+            return Ok(true);
+        }
+        let glyph = Span { len: 1, ..start };
+        match c {
+            '[' => {
+                chars.next();
+                let body = self.read_bracketed(chars, glyph, ']', Parser::read_one)?;
+                self.push(CatCommand::Block(body), chars.span_from(start));
+                return Ok(true);
             }
-            '@' => {
-                if !self.read_command_block(chars) {
-                    return false;
-                }
-                no_next = true;
-                CatCommand::ForEach
+            '(' => {
+                chars.next();
+                let body = self.read_bracketed(chars, glyph, ')', Parser::read_one)?;
+                self.push(CatCommand::List(body), chars.span_from(start));
+                return Ok(true);
             }
-            '#' => {
-                if !self.read_command_block(chars) {
-                    return false;
-                }
-                no_next = true;
-                CatCommand::Repeat
+            _ => {}
+        }
+        let info = match builtins::by_glyph(c) {
+            Some(info) => info,
+            None => return Ok(false),
+        };
+        match info.rule {
+            ParseRule::Plain(make) => {
+                chars.next();
+                self.push(make(), glyph);
+            }
+            ParseRule::ImplicitBlock(make) => {
+                self.read_command_block(chars)?;
+                self.push(make(), glyph);
             }
-            '!' => CatCommand::Execute,
-            'S' => CatCommand::Split,
-            'I' => CatCommand::ToInteger,
-            'r' => CatCommand::Range,
-            ':' => CatCommand::Duplicate,
-            ';' => CatCommand::DuplicateSecond,
-            '_' => CatCommand::Drop,
-            'x' => CatCommand::Rotate(2),
-            'X' => CatCommand::Rotate(3),
-            'p' => CatCommand::PushSide,
-            'P' => CatCommand::PopSide,
-            '~' => CatCommand::ConsumeSide,
-            'J' => CatCommand::Join,
-            '>' => {
+            ParseRule::ReadsVariable(make) => {
                 chars.next();
-                match chars.peek() {
-                    Some(c) => {
-                        if !self.known_variables.contains(c) {
-                            self.known_variables.push(*c);
-                        }
-                        CatCommand::PushVariable(*c)
+                let name = match chars.next() {
+                    Some(c) => c,
+                    None => return Err(KatError::Parse(ParseError::UnexpectedEof, chars.here())),
+                };
+                let command = make(name);
+                if let CatCommand::PushVariable(name) = command {
+                    if !self.known_variables.contains(&name) {
+                        self.known_variables.push(name);
                     }
-                    None => return false,
                 }
+                self.push(command, chars.span_from(start));
             }
-            '<' => {
+            ParseRule::QuotesCommand => {
                 chars.next();
-                match chars.peek() {
-                    Some(c) => CatCommand::PopVariable(*c, false),
-                    None => return false,
+                if !self.nested(chars, glyph, Parser::read_command)? {
+                    let err = match chars.peek() {
+                        Some(&c) => ParseError::UnexpectedCharacter(c),
+                        None => ParseError::UnexpectedEof,
+                    };
+                    return Err(KatError::Parse(
+                        err,
+                        Span {
+                            len: 1,
+                            ..chars.here()
+                        },
+                    ));
                 }
+                let f = self.commands.pop().unwrap();
+                self.push(
+                    CatCommand::CreateCommand(Box::new(f)),
+                    chars.span_from(start),
+                );
             }
-            _ => return false,
-        };
-        if !no_next {
-            chars.next();
-        }
-        self.commands.push(cmd);
-        if c == ')' {
-            self.commands.push(CatCommand::ExecuteScoped);
         }
-        return true;
+        Ok(true)
     }
 
-    fn read_command_block<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) -> bool {
+    /// Reads the implicit block following a command glyph. The block ends at
+    /// a `$` (which is consumed), at the end of the enclosing block or at EOF.
+    fn read_command_block(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
         chars.next();
         if let Some(&c) = chars.peek() {
-            match c {
-                '$' => {
-                    chars.next();
-                    return true;
-                }
-                ')' => return true,
-                ']' => return true,
-                '}' => return true,
-                _ => {}
+            if c == '$' {
+                chars.next();
+                return Ok(());
+            } else if CLOSERS.contains(&c) {
+                return Ok(());
             }
         }
-        self.commands.push(CatCommand::StartBlock);
-        loop {
-            match self.read_one(chars, &[')', ']', '}']) {
-                ReadResult::Ok => {}
-                ReadResult::NoMatch('$') => {
-                    chars.next();
-                    break;
-                }
-                ReadResult::NoMatch(')') => break,
-                ReadResult::NoMatch(']') => break,
-                ReadResult::NoMatch('}') => break,
-                ReadResult::NoMatch(_) => return false,
-                ReadResult::Done => break,
-            }
+        let open = Span { len: 1, ..start };
+        let (body, terminator) = self.read_body(chars, open, Parser::read_one)?;
+        if terminator == Some('$') {
+            chars.next();
         }
-        self.commands.push(CatCommand::CloseBlock);
-        true
+        self.push(CatCommand::Block(body), chars.span_from(start));
+        Ok(())
     }
 
-    fn read_named_block<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) -> bool {
+    fn read_named_block(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
         chars.next();
-        self.commands.push(CatCommand::StartBlock);
-        loop {
-            match self.read_one(chars, &['}']) {
-                ReadResult::Ok => {}
-                ReadResult::NoMatch('}') => {
-                    chars.next();
-                    break;
-                }
-                ReadResult::NoMatch(_) => return false,
-                ReadResult::Done => break,
-            }
-        }
-        self.commands.push(CatCommand::CloseBlock);
+        let body = self.read_bracketed(chars, Span { len: 1, ..start }, '}', Parser::read_one)?;
+        self.push(CatCommand::Block(body), chars.span_from(start));
+        let name_start = chars.here();
         let name = match chars.next() {
             Some(c) => c,
-            None => return false,
+            None => return Err(KatError::Parse(ParseError::UnexpectedEof, name_start)),
         };
-        self.commands.push(CatCommand::PushVariable(name));
+        self.push(CatCommand::PushVariable(name), chars.span_from(name_start));
         if !self.known_variables.contains(&name) {
             self.known_variables.push(name);
         }
-        true
+        Ok(())
     }
 
-    fn read_pre_named_block<I: Iterator<Item = char>>(&mut self, chars: &mut Peekable<I>) -> bool {
+    /// Reads a block named by an unknown variable. The definition is hoisted
+    /// to the start of the program, and the point of definition fetches it.
+    fn read_pre_named_block(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
         let name = match chars.next() {
             Some(c) => c,
-            None => return false,
+            None => return Err(KatError::Parse(ParseError::UnexpectedEof, start)),
         };
-        let cmd_pos = self.commands.len();
-        self.commands.push(CatCommand::StartBlock);
-        loop {
-            match self.read_one(chars, &[')', ']', '}']) {
-                ReadResult::Ok => {}
-                ReadResult::NoMatch('$') => {
+        let name_span = chars.span_from(start);
+        let (body, terminator) = self.read_body(chars, name_span, Parser::read_one)?;
+        if terminator == Some('$') || terminator == Some('}') {
+            chars.next();
+        }
+        let definition = vec![
+            Command::new(CatCommand::Block(body), chars.span_from(start)),
+            Command::new(CatCommand::PushVariable(name), name_span),
+        ];
+        self.hoisted.splice(0..0, definition);
+        self.push(CatCommand::PopVariable(name, true), name_span);
+        if !self.known_variables.contains(&name) {
+            self.known_variables.push(name);
+        }
+        Ok(())
+    }
+
+    /// Reads the next command of verbose source, skipping whitespace and
+    /// comments before it.
+    fn read_verbose_one(&mut self, chars: &mut Cursor) -> Result<ReadResult, KatError> {
+        skip_space(chars);
+        let c = if let Some(c) = chars.peek() {
+            *c
+        } else {
+            return Ok(ReadResult::Done);
+        };
+        let start = chars.here();
+        if c == '"' {
+            self.read_string(chars)?;
+        } else if c == '«' {
+            self.read_raw_string(chars)?;
+        } else if c == '\'' {
+            self.read_char(chars)?;
+        } else if c.is_ascii_digit()
+            || (c == '¯' && chars.peek_second().is_some_and(|c| c.is_ascii_digit()))
+        {
+            self.read_digit(chars);
+        } else if c == '[' || c == '(' {
+            chars.next();
+            let glyph = Span { len: 1, ..start };
+            if c == '[' {
+                let body = self.read_bracketed(chars, glyph, ']', Parser::read_verbose_one)?;
+                self.push(CatCommand::Block(body), chars.span_from(start));
+            } else {
+                let body = self.read_bracketed(chars, glyph, ')', Parser::read_verbose_one)?;
+                self.push(CatCommand::List(body), chars.span_from(start));
+            }
+        } else if is_word_char(c) {
+            self.read_word(chars)?;
+        } else {
+            return Ok(ReadResult::NoMatch(c));
+        }
+        Ok(ReadResult::Ok)
+    }
+
+    /// Reads a word of verbose source, along with the variable name, the
+    /// implicit block or the quoted command that follows it.
+    fn read_word(&mut self, chars: &mut Cursor) -> Result<(), KatError> {
+        let start = chars.here();
+        let mut word = String::new();
+        while let Some(&c) = chars.peek() {
+            if !is_word_char(c) {
+                break;
+            }
+            word.push(c);
+            chars.next();
+        }
+        let span = chars.span_from(start);
+        let native = self.natives.iter().find(|n| n.name() == word).cloned();
+        if let Some(native) = native {
+            self.push(CatCommand::Native(native), span);
+            return Ok(());
+        }
+        if word == "call" {
+            let name = self.read_verbose_name(chars)?;
+            self.push(CatCommand::PopVariable(name, true), chars.span_from(start));
+            return Ok(());
+        }
+        let info = match builtins::by_word(&word) {
+            Some(info) => info,
+            None => return Err(KatError::Parse(ParseError::UnknownWord(word), span)),
+        };
+        match info.rule {
+            ParseRule::Plain(make) => self.push(make(), span),
+            ParseRule::ImplicitBlock(make) => {
+                skip_space(chars);
+                if chars.peek() == Some(&'{') {
+                    let open = chars.here();
                     chars.next();
-                    break;
+                    let body = self.read_bracketed(
+                        chars,
+                        Span { len: 1, ..open },
+                        '}',
+                        Parser::read_verbose_one,
+                    )?;
+                    self.push(CatCommand::Block(body), chars.span_from(start));
                 }
-                ReadResult::NoMatch('}') => {
-                    chars.next();
-                    break;
+                self.push(make(), span);
+            }
+            ParseRule::ReadsVariable(make) => {
+                let name = self.read_verbose_name(chars)?;
+                let command = make(name);
+                if let CatCommand::PushVariable(name) = command {
+                    if !self.known_variables.contains(&name) {
+                        self.known_variables.push(name);
+                    }
                 }
-                ReadResult::NoMatch(')') => break,
-                ReadResult::NoMatch(']') => break,
-                ReadResult::NoMatch(_) => return false,
-                ReadResult::Done => break,
+                self.push(command, chars.span_from(start));
+            }
+            ParseRule::QuotesCommand => {
+                skip_space(chars);
+                let target = chars.here();
+                // Like in terse source, only commands and blocks can be
+                // quoted, and executing a variable can't.
+                let err = match chars.peek() {
+                    Some(&c) if c == '[' || c == '(' || is_word_char(c) => {
+                        if c.is_ascii_digit() {
+                            Some(ParseError::UnexpectedCharacter(c))
+                        } else {
+                            self.nested(chars, span, Parser::read_verbose_one)?;
+                            match self.commands.last().map(|f| &f.kind) {
+                                Some(CatCommand::PopVariable(_, true)) => {
+                                    Some(ParseError::UnexpectedCharacter(c))
+                                }
+                                _ => None,
+                            }
+                        }
+                    }
+                    Some(&c) => Some(ParseError::UnexpectedCharacter(c)),
+                    None => Some(ParseError::UnexpectedEof),
+                };
+                if let Some(err) = err {
+                    return Err(KatError::Parse(err, Span { len: 1, ..target }));
+                }
+                let f = self.commands.pop().unwrap();
+                self.push(
+                    CatCommand::CreateCommand(Box::new(f)),
+                    chars.span_from(start),
+                );
             }
         }
-        self.commands.push(CatCommand::CloseBlock);
-        self.commands.push(CatCommand::PushVariable(name));
-        {
-            let prec = self.commands.drain(cmd_pos..).collect::<Vec<_>>();
-            self.commands.splice(0..0, prec);
-        }
-        self.commands.push(CatCommand::PopVariable(name, true));
-        if !self.known_variables.contains(&name) {
-            self.known_variables.push(name);
+        Ok(())
+    }
+
+    /// Reads the variable name after a word, skipping whitespace before it.
+    fn read_verbose_name(&mut self, chars: &mut Cursor) -> Result<char, KatError> {
+        skip_space(chars);
+        match chars.next() {
+            Some(c) => Ok(c),
+            None => Err(KatError::Parse(ParseError::UnexpectedEof, chars.here())),
         }
-        true
     }
 }
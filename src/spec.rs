@@ -1,32 +1,149 @@
-#[allow(dead_code)]
-#[derive(Clone, Debug)]
+use builtins;
+use integer::Integer;
+use native::NativeCommand;
+use std::fmt;
+
+#[derive(Clone, PartialEq)]
 pub enum CatCommand {
-    StartBlock,
-    CloseBlock,
-    CreateInteger(i64),
+    /// A quoted block, pushed to the stack as a list of commands.
+    Block(Vec<Command>),
+    /// A list literal, executed in a separate context and collected to a list.
+    List(Vec<Command>),
+    CreateInteger(Integer),
+    CreateFloat(f64),
     CreateString(String),
-    CreateCommand(Box<CatCommand>),
+    CreateCommand(Box<Command>),
     Write,
     WriteLine,
     ReadLine,
     Add,
+    Subtract,
     Multiply,
+    Divide,
+    Modulo,
+    Power,
+    Negate,
+    Abs,
+    Min,
+    Max,
+    Sign,
+    SquareRoot,
+    ToFloat,
     Execute,
-    ExecuteScoped,
     Map,
     ForEach,
+    Filter,
+    Fold,
+    Scan,
+    Sort,
+    SortBy,
+    Group,
+    Count,
+    Unique,
     Repeat,
+    If,
+    IfElse,
+    While,
+    Until,
+    FixedPoint,
     Split,
     ToInteger,
     Range,
     Duplicate,
     DuplicateSecond,
     Drop,
-    Rotate(usize),
+    Rotate(Rotation),
     PushSide,
     PopSide,
     ConsumeSide,
     Join,
+    Equal,
+    Less,
+    Greater,
+    Not,
+    And,
+    Or,
     PushVariable(char),
     PopVariable(char, bool),
+    /// A command registered by the embedding program.
+    Native(NativeCommand),
+}
+
+/// How many items `Rotate` rotates. Only the depths with a glyph exist, so
+/// every command has a builtin to describe it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Two,
+    Three,
+}
+
+impl Rotation {
+    pub fn depth(self) -> usize {
+        match self {
+            Rotation::Two => 2,
+            Rotation::Three => 3,
+        }
+    }
+}
+
+/// Builtins are shown with their name from `BUILTINS`, and literals and
+/// blocks with their contents.
+impl fmt::Debug for CatCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(info) = builtins::info(self) {
+            return match self {
+                CatCommand::PushVariable(c) | CatCommand::PopVariable(c, _) => {
+                    f.debug_tuple(info.name).field(c).finish()
+                }
+                CatCommand::CreateCommand(v) => f.debug_tuple(info.name).field(v).finish(),
+                _ => f.write_str(info.name),
+            };
+        }
+        match self {
+            CatCommand::Block(v) => f.debug_tuple("Block").field(v).finish(),
+            CatCommand::List(v) => f.debug_tuple("List").field(v).finish(),
+            CatCommand::CreateInteger(v) => f.debug_tuple("CreateInteger").field(v).finish(),
+            CatCommand::CreateFloat(v) => f.debug_tuple("CreateFloat").field(v).finish(),
+            CatCommand::CreateString(v) => f.debug_tuple("CreateString").field(v).finish(),
+            CatCommand::Native(v) => f.debug_tuple("Native").field(v).finish(),
+            CatCommand::PopVariable(c, _) => f.debug_tuple("ExecuteVariable").field(c).finish(),
+            _ => unreachable!("Builtin command missing from BUILTINS"),
+        }
+    }
+}
+
+/// A region of the source code. `offset` and `len` are counted in characters,
+/// `line` and `column` start from 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+    pub len: usize,
+}
+
+/// A command together with the source span it was parsed from.
+#[derive(Clone)]
+pub struct Command {
+    pub kind: CatCommand,
+    pub span: Span,
+}
+
+impl Command {
+    pub fn new(kind: CatCommand, span: Span) -> Command {
+        Command { kind, span }
+    }
+}
+
+/// Commands compare equal regardless of where they were parsed from.
+impl PartialEq for Command {
+    fn eq(&self, other: &Command) -> bool {
+        self.kind == other.kind
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.kind.fmt(f)
+    }
 }
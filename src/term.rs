@@ -1,10 +1,26 @@
-use interpreter::Interpreter;
+use interpreter::{CapturedOutput, Interpreter, Limits};
 use itertools::Itertools;
+use native::Natives;
 use parser::Parser;
-use std::io::{stdin, stdout, Write};
+use std::io::{self, stdin, stdout, Write};
+use std::time::Duration;
 use termion::{clear, cursor, event::Key, input::TermRead, raw::IntoRawMode, terminal_size};
 
-pub fn run_term() -> Result<(), String> {
+/// The program is re-run on every keystroke, so runaway programs are cut
+/// short unless the user asks for other limits.
+const DEFAULT_MAX_STEPS: u64 = 100_000;
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(200);
+const DEFAULT_MAX_MEMORY: usize = 64 * 1024 * 1024;
+
+/// Runs the interactive edit mode. Limits that aren't set get defaults
+/// suitable for re-running on every keystroke.
+pub fn run_term(limits: &Limits, natives: &Natives) -> Result<(), String> {
+    let limits = Limits {
+        max_steps: limits.max_steps.or(Some(DEFAULT_MAX_STEPS)),
+        timeout: limits.timeout.or(Some(DEFAULT_TIMEOUT)),
+        max_memory: limits.max_memory.or(Some(DEFAULT_MAX_MEMORY)),
+        max_depth: limits.max_depth,
+    };
     let stdin = stdin();
     // Enter raw mode.
     let mut stdout = stdout().into_raw_mode().unwrap();
@@ -21,7 +37,8 @@ pub fn run_term() -> Result<(), String> {
         clear::CurrentLine,
         code,
         cursor::Show
-    ).unwrap();
+    )
+    .unwrap();
 
     stdout.flush().unwrap();
     for c in stdin.keys() {
@@ -30,25 +47,23 @@ pub fn run_term() -> Result<(), String> {
             // Exit.
             Key::Esc => break,
             Key::Char(c) => {
-                code.insert(cursorpos, c);
+                code.insert(byte_index(&code, cursorpos), c);
                 cursorpos += 1;
             }
             Key::Backspace => {
-                if code.len() > 0 {
-                    code.remove(cursorpos - 1);
+                if cursorpos > 0 {
                     cursorpos -= 1;
+                    code.remove(byte_index(&code, cursorpos));
                 }
             }
             Key::Left => {
-                if cursorpos > 0 {
-                    cursorpos -= 1;
-                }
+                cursorpos = cursorpos.saturating_sub(1);
                 write!(stdout, "{}", cursor::Goto(3 + cursorpos as u16, 2),).unwrap();
                 stdout.flush().unwrap();
                 continue;
             }
             Key::Right => {
-                if cursorpos < code.len() {
+                if cursorpos < code.chars().count() {
                     cursorpos += 1;
                 }
                 write!(stdout, "{}", cursor::Goto(3 + cursorpos as u16, 2),).unwrap();
@@ -63,7 +78,8 @@ pub fn run_term() -> Result<(), String> {
             "{}{}Write code below:",
             clear::All,
             cursor::Goto(1, 1)
-        ).unwrap();
+        )
+        .unwrap();
 
         write!(
             stdout,
@@ -77,50 +93,47 @@ pub fn run_term() -> Result<(), String> {
                     c.to_string()
                 })
                 .join("")
-        ).unwrap();
+        )
+        .unwrap();
 
-        let mut parser = Parser::new();
+        let mut parser = Parser::with_natives(natives.clone());
         match parser.parse(&code) {
             Ok(()) => {}
             Err(e) => {
-                write!(
-                    stdout,
-                    "{}{}Parse error: {}",
-                    cursor::Goto(1, 3),
-                    clear::CurrentLine,
-                    e
-                ).unwrap();
+                write!(stdout, "{}{}{}", cursor::Goto(1, 3), clear::CurrentLine, e).unwrap();
             }
         }
-        let mut interpreter = Interpreter::new(false);
+        let output = CapturedOutput::new();
+        let mut interpreter =
+            Interpreter::with_io(false, Box::new(io::empty()), Box::new(output.clone()));
+        interpreter.set_limits(&limits);
         match interpreter.execute(parser.commands.iter()) {
             Ok(()) => {}
             Err(e) => {
-                write!(
-                    stdout,
-                    "{}{}Execution error: {}",
-                    cursor::Goto(1, 3),
-                    clear::CurrentLine,
-                    e
-                ).unwrap();
+                write!(stdout, "{}{}{}", cursor::Goto(1, 3), clear::CurrentLine, e).unwrap();
             }
         }
         write!(
             stdout,
-            "{}{:<40} | {:<40} | {:<40}",
+            "{}{:<40} | {:<40} | {:<40} | {:<40}",
             cursor::Goto(1, 4),
             "Commands",
             "Stack",
-            "Side stack"
-        ).unwrap();
+            "Side stack",
+            "Output"
+        )
+        .unwrap();
         let (_width, height) = terminal_size().unwrap_or((80, 30));
+        let output = output.contents();
+        let output_lines: Vec<&str> = output.lines().collect();
         for i in 0..(height - 5) as usize {
             let cmd = parser.commands.get(i);
             let stack_item = interpreter.main_stack.get(i);
             let side_item = interpreter.side_stack.get(i);
+            let output_line = output_lines.get(i);
             write!(
                 stdout,
-                "{}{:<40} | {:<40} | {:<40}",
+                "{}{:<40} | {:<40} | {:<40} | {:<40}",
                 cursor::Goto(1, 5 + i as u16),
                 cmd.map(|x| format!("{:?}", x)).unwrap_or("".to_owned()),
                 stack_item
@@ -128,8 +141,12 @@ pub fn run_term() -> Result<(), String> {
                     .unwrap_or("".to_owned()),
                 side_item
                     .map(|x| x.debug_stringify().chars().take(40).join(""))
+                    .unwrap_or("".to_owned()),
+                output_line
+                    .map(|x| x.chars().take(40).join(""))
                     .unwrap_or("".to_owned())
-            ).unwrap()
+            )
+            .unwrap()
         }
 
         write!(stdout, "{}", cursor::Goto(3 + cursorpos as u16, 2),).unwrap();
@@ -140,3 +157,11 @@ pub fn run_term() -> Result<(), String> {
 
     Ok(())
 }
+
+/// The byte index of the character at `position`, or the end of `code`.
+/// The cursor counts characters, and most glyphs are more than one byte.
+fn byte_index(code: &str, position: usize) -> usize {
+    code.char_indices()
+        .nth(position)
+        .map_or(code.len(), |(i, _)| i)
+}
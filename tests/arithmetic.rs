@@ -0,0 +1,67 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::error::{KatError, RuntimeError};
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+fn error(code: &str) -> RuntimeError {
+    match run(code, "", &Limits::default()) {
+        Err(KatError::Runtime(e, _)) => e,
+        result => panic!("{:?}", result.map(|out| render_stack(&out.stack))),
+    }
+}
+
+#[test]
+fn division_rounds_towards_negative_infinity() {
+    assert_eq!(stack("7 2/"), "3");
+    assert_eq!(stack("¯7 2/"), "¯4");
+    assert_eq!(stack("7 ¯2/"), "¯4");
+}
+
+#[test]
+fn modulo_has_the_sign_of_the_divisor() {
+    assert_eq!(stack("7 3%"), "1");
+    assert_eq!(stack("¯7 2%"), "1");
+    assert_eq!(stack("7 ¯2%"), "¯1");
+}
+
+#[test]
+fn dividing_integers_by_zero_fails() {
+    assert_eq!(error("1 0/"), RuntimeError::DivisionByZero);
+    assert_eq!(error("1 0%"), RuntimeError::DivisionByZero);
+}
+
+#[test]
+fn power_of_a_negative_exponent_is_a_float() {
+    assert_eq!(stack("2 10^"), "1024");
+    assert_eq!(stack("2 ¯1^"), "0.5");
+}
+
+#[test]
+fn subtract_negate_abs_sign_min_and_max() {
+    assert_eq!(stack("7 2-"), "5");
+    assert_eq!(stack("5±"), "¯5");
+    assert_eq!(stack("¯5¦"), "5");
+    assert_eq!(stack("¯3§0§4§"), "¯1 0 1");
+    assert_eq!(stack("3 5⌊"), "3");
+    assert_eq!(stack("3 5⌈"), "5");
+}
+
+#[test]
+fn arithmetic_maps_over_lists() {
+    assert_eq!(stack("(1 2 3)2-"), "(¯1 0 1)");
+    assert_eq!(stack("(1 2 3)±"), "(¯1 ¯2 ¯3)");
+    assert_eq!(stack("(¯1 2)¦"), "(1 2)");
+    assert_eq!(stack("(1 5)3⌊"), "(1 3)");
+}
+
+#[test]
+fn overflow_promotes_instead_of_wrapping() {
+    assert_eq!(stack("9223372036854775807 1+"), "9223372036854775808");
+    assert_eq!(stack("¯9223372036854775808±"), "9223372036854775808");
+    assert_eq!(stack("¯9223372036854775808 1-"), "¯9223372036854775809");
+}
@@ -0,0 +1,57 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+#[test]
+fn not_tests_whole_lists() {
+    assert_eq!(stack("()N"), "1");
+    assert_eq!(stack("(0)N"), "0");
+    assert_eq!(stack("(1 2)N"), "0");
+    assert_eq!(stack("(1 0)&N"), "(0 1)");
+}
+
+#[test]
+fn and_and_or_test_whole_lists() {
+    assert_eq!(stack("()1A"), "0");
+    assert_eq!(stack("(0)1A"), "1");
+    assert_eq!(stack("()(0 0)O"), "1");
+    assert_eq!(stack("()\"\"O"), "0");
+}
+
+#[test]
+fn equality_is_structural() {
+    assert_eq!(stack("1 1="), "1");
+    assert_eq!(stack("1 1.0="), "1");
+    assert_eq!(stack("\"a\"\"a\"="), "1");
+    assert_eq!(stack("1\"1\"="), "0");
+    assert_eq!(stack("(1(2 3))(1(2 3))="), "1");
+    assert_eq!(stack("(1(2))(1(3))="), "0");
+    assert_eq!(stack("`+`+="), "1");
+    assert_eq!(stack("`+`*="), "0");
+}
+
+#[test]
+fn comparisons_order_lists_item_by_item() {
+    assert_eq!(stack("(1 2)(1 3)L"), "1");
+    assert_eq!(stack("(1 2)(1 2 0)L"), "1");
+    assert_eq!(stack("(1 2)(1 2 0)G"), "0");
+    assert_eq!(stack("\"b\"\"a\"G"), "1");
+    assert_eq!(stack("\"a\"\"b\"L"), "1");
+}
+
+#[test]
+fn numbers_are_ordered_before_strings() {
+    assert_eq!(stack("1\"a\"L"), "1");
+    assert_eq!(stack("(\"a\")((1))L"), "1");
+}
+
+#[test]
+fn comparisons_map_over_a_list_and_a_number() {
+    assert_eq!(stack("(1 2 3)2L"), "(1 0 0)");
+    assert_eq!(stack("(1 2 3)2="), "(0 1 0)");
+}
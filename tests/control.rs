@@ -0,0 +1,53 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+#[test]
+fn if_runs_its_block_on_true() {
+    assert_eq!(stack("1?5$"), "5");
+    assert_eq!(stack("0?5$"), "");
+    assert_eq!(stack("1[5]?$"), "5");
+    assert_eq!(stack("0[5]?$"), "");
+}
+
+#[test]
+fn if_else_picks_a_block() {
+    assert_eq!(stack("1[1]|2"), "1");
+    assert_eq!(stack("0[1]|2"), "2");
+    assert_eq!(stack("1[1][2]|$"), "1");
+    assert_eq!(stack("0[1][2]|$"), "2");
+}
+
+#[test]
+fn conditions_use_truthiness() {
+    assert_eq!(stack("\"\"?1$"), "");
+    assert_eq!(stack("\"a\"?1$"), "1");
+    assert_eq!(stack("()?1$"), "");
+    assert_eq!(stack("(0)?1$"), "1");
+    assert_eq!(stack("0.0?1$"), "");
+    assert_eq!(stack("¯0.5?1$"), "1");
+    assert_eq!(stack("`+?1$"), "1");
+}
+
+#[test]
+fn while_loops_until_the_condition_is_false() {
+    assert_eq!(stack("5:¡1-:$"), "0");
+    assert_eq!(stack("5:¡p1-:$~"), "0 (5 4 3 2 1)");
+    assert_eq!(stack("5 0¡1-:$"), "5");
+}
+
+#[test]
+fn until_loops_until_the_condition_is_true() {
+    assert_eq!(stack("0 0¿1+:5=$"), "5");
+    assert_eq!(stack("5 1¿1-:$"), "5");
+}
+
+#[test]
+fn fixed_point_stops_when_the_top_stops_changing() {
+    assert_eq!(stack("100Y2/1+$"), "2");
+}
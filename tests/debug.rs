@@ -0,0 +1,21 @@
+extern crate katlang;
+
+use katlang::parse;
+
+fn debug(code: &str) -> String {
+    format!("{:?}", parse(code).unwrap())
+}
+
+#[test]
+fn integers_show_the_plain_number() {
+    assert_eq!(debug("3"), "[CreateInteger(3)]");
+    assert_eq!(
+        debug("¯123456789012345678901234567890"),
+        "[CreateInteger(-123456789012345678901234567890)]"
+    );
+}
+
+#[test]
+fn rotations_show_their_depth() {
+    assert_eq!(debug("xX"), "[Rotate(2), Rotate(3)]");
+}
@@ -0,0 +1,77 @@
+//! Runs the katlang snippets in the documentation, like `katlang test-docs`.
+
+extern crate katlang;
+
+use katlang::doctest::{self, Snippet};
+use std::fs;
+use std::thread;
+
+fn check_file(name: &'static str) {
+    let path = format!("{}/{}", env!("CARGO_MANIFEST_DIR"), name);
+    let text = fs::read_to_string(path).unwrap();
+    let snippets = doctest::snippets(&text).unwrap();
+    // Nested blocks execute recursively, so give them room like the CLI does.
+    let failures = thread::Builder::new()
+        .stack_size(1 << 28)
+        .spawn(move || {
+            snippets
+                .iter()
+                .filter_map(|snippet| {
+                    snippet
+                        .check()
+                        .err()
+                        .map(|e| format!("{}:{}: {}", name, snippet.line, e))
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn readme() {
+    check_file("README.md");
+}
+
+#[test]
+fn spec() {
+    check_file("spec.md");
+}
+
+#[test]
+fn examples() {
+    check_file("examples.md");
+}
+
+#[test]
+fn expectations_follow_their_snippet() {
+    let text = "```katlang\nRW\n```\n\n```input\nhi\n```\n```output\nhi\n```\n";
+    let snippets = doctest::snippets(text).unwrap();
+    assert_eq!(
+        snippets,
+        vec![Snippet {
+            line: 1,
+            code: "RW".to_owned(),
+            input: "hi\n".to_owned(),
+            stack: None,
+            output: Some("hi\n".to_owned()),
+        }]
+    );
+    assert!(snippets[0].check().is_ok());
+
+    let orphan = "```katlang\n1\n```\nSome text.\n```stack\n1\n```\n";
+    assert!(doctest::snippets(orphan).is_err());
+}
+
+#[test]
+fn mismatches_are_reported() {
+    let text = "  ```katlang\n  1 2+\n  ```\n\n  ```stack\n  4\n  ```\n";
+    let snippets = doctest::snippets(text).unwrap();
+    assert_eq!(snippets[0].code, "1 2+");
+    assert_eq!(
+        snippets[0].check(),
+        Err("expected the stack 4, got 3".to_owned())
+    );
+}
@@ -0,0 +1,129 @@
+extern crate katlang;
+
+use katlang::builtins::BUILTINS;
+use katlang::emit::emit;
+use katlang::error::{EmitError, KatError};
+use katlang::{parse, parse_verbose};
+
+/// Checks that the emitted source parses to the same commands, and that
+/// emitting is stable.
+fn round_trip(code: &str) -> String {
+    let commands = parse(code).unwrap();
+    let emitted = emit(&commands).unwrap();
+    let reparsed =
+        parse(&emitted).unwrap_or_else(|e| panic!("{:?} emitted as {:?}: {}", code, emitted, e));
+    assert_eq!(reparsed, commands, "{:?} emitted as {:?}", code, emitted);
+    assert_eq!(emit(&reparsed).unwrap(), emitted);
+    emitted
+}
+
+#[test]
+fn emits_shortest_forms() {
+    assert_eq!(round_trip("\"x\""), "'x");
+    assert_eq!(round_trip("(1 2 3)[1+]&$"), "(1 2 3)&1+");
+    assert_eq!(round_trip("(1 2 3)&1+$W"), "(1 2 3)&1+$W");
+    assert_eq!(round_trip("([1+]&$)"), "(&1+)");
+    assert_eq!(round_trip("[1+]>f"), "{1+}f");
+    assert_eq!(round_trip("1 2+"), "1 2+");
+    assert_eq!(round_trip("1 ¯2 3.0+"), "1¯2 3.0+");
+    assert_eq!(round_trip("1  2"), "1  2");
+    assert_eq!(round_trip("\"a\\\"b\""), "«a\"b»");
+    assert_eq!(round_trip("\"a\\\"»\""), "\"a\\\"»\"");
+    assert_eq!(round_trip("\"a\\nb\""), "\"a\nb\"");
+    assert_eq!(round_trip("D:+}5D"), "D:+}5D");
+    assert_eq!(round_trip("{:+}D5D"), "5D:+");
+}
+
+#[test]
+fn implicit_blocks_keep_their_terminators() {
+    assert_eq!(round_trip("&$"), "&$");
+    assert_eq!(round_trip("(&)"), "(&)");
+    assert_eq!(round_trip("&"), "&");
+    assert_eq!(round_trip("[]&$W"), "[]&$W");
+    assert_eq!(round_trip("&&1$$"), "&&1");
+    assert_eq!(round_trip("&&1$$2"), "&&1$$2");
+    assert_eq!(round_trip("`&1+$W"), "`&1+$W");
+}
+
+#[test]
+fn variables_next_to_numbers_round_trip() {
+    assert_eq!(round_trip("5¯¶D2a;>¯\n8a."), "5¯¶\n8a.");
+    assert_eq!(round_trip("1>¯¯¶\n0"), "1>¯¯¶\n0");
+    assert_eq!(round_trip("1>¯¯¯2"), "1>¯¯¯2");
+}
+
+/// Emits a verbose program, which can express commands terse source can't.
+fn emit_verbose(code: &str) -> Result<String, KatError> {
+    emit(&parse_verbose(code).unwrap())
+}
+
+#[test]
+fn variables_named_by_glyphs_are_refused() {
+    match emit_verbose("[dup] set r 2 call r") {
+        Err(KatError::Emit(EmitError::UnwritableVariable('r'), span)) => {
+            assert_eq!(span.column, 15)
+        }
+        other => panic!("{:?}", other),
+    }
+    match emit_verbose("set 1 2 call 1") {
+        Err(KatError::Emit(EmitError::UnwritableVariable('1'), _)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn high_minus_before_a_number_is_separated() {
+    let commands = parse_verbose("[dup] set ¯ 1 call ¯ call ¯ 5").unwrap();
+    let emitted = emit(&commands).unwrap();
+    assert_eq!(emitted, "1¯:}¯¶\n5");
+    assert_eq!(parse(&emitted).unwrap(), commands);
+}
+
+#[test]
+fn unassigned_variables_are_refused() {
+    match emit_verbose("call z") {
+        Err(KatError::Emit(EmitError::UndefinedVariable('z'), _)) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn builtin_examples_round_trip() {
+    for info in BUILTINS {
+        for example in info.examples {
+            round_trip(example.code);
+        }
+    }
+}
+
+/// A small xorshift generator, so the programs are the same on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn random_programs_round_trip() {
+    let alphabet: Vec<char> = "0123456789¯. \n\"'\\`[](){}$<>&@?|#:;+axWvD√±¿«»¶"
+        .chars()
+        .collect();
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    let mut parsed = 0;
+    for _ in 0..20_000 {
+        let len = rng.next() % 12;
+        let code: String = (0..len)
+            .map(|_| alphabet[(rng.next() % alphabet.len() as u64) as usize])
+            .collect();
+        if parse(&code).is_ok() {
+            round_trip(&code);
+            parsed += 1;
+        }
+    }
+    assert!(parsed > 1000, "only {} programs parsed", parsed);
+}
@@ -0,0 +1,40 @@
+extern crate katlang;
+
+use katlang::explain::explain;
+use katlang::parse;
+
+fn explained(code: &str) -> String {
+    explain(code, &parse(code).unwrap())
+}
+
+#[test]
+fn implicit_blocks_are_nested_under_their_command() {
+    assert_eq!(
+        explained("(1 2)&1+"),
+        "(1 2)&1+
+(         List      -- list
+ 1          Push 1  -- n
+   2        Push 2  -- n
+    )
+     &    Map       list f -- list
+      1     Push 1  -- n
+       +    Add     a b -- c
+"
+    );
+}
+
+#[test]
+fn hoisted_definitions_stay_in_place() {
+    assert_eq!(
+        explained("5D:+}D"),
+        "5D:+}D
+5       Push 5             -- n
+ D      Block              -- f
+  :       Duplicate        a -- a a
+   +      Add              a b -- c
+ D      PushVariable D     a --
+ D      ExecuteVariable D
+     D  ExecuteVariable D
+"
+    );
+}
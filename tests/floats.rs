@@ -0,0 +1,54 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+fn output(code: &str) -> String {
+    run(code, "", &Limits::default()).unwrap().output
+}
+
+#[test]
+fn literals_with_a_point_are_floats() {
+    assert_eq!(stack("3.14"), "3.14");
+    assert_eq!(stack("2.0"), "2.0");
+    assert_eq!(stack("¯2.5"), "¯2.5");
+}
+
+#[test]
+fn a_float_operand_makes_the_result_a_float() {
+    assert_eq!(stack("1 2.5+"), "3.5");
+    assert_eq!(stack("2 1.5*"), "3.0");
+    assert_eq!(stack("7 2.0/"), "3.5");
+    assert_eq!(stack("1 3.0/"), "0.3333333333333333");
+    assert_eq!(stack("2 0.5^"), "1.4142135623730951");
+}
+
+#[test]
+fn float_division_by_zero_follows_ieee() {
+    assert_eq!(stack("1.0 0/"), "inf");
+    assert_eq!(stack("1.0 0/±"), "¯inf");
+    assert_eq!(stack("¯1√"), "NaN");
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(stack("4√"), "2.0");
+    assert_eq!(stack("3·"), "3.0");
+    assert_eq!(stack("\"2.5\"·"), "2.5");
+    assert_eq!(stack("2.5I"), "2");
+    assert_eq!(stack("¯2.5I"), "¯2");
+    assert!(run("\"abc\"·", "", &Limits::default()).is_err());
+}
+
+#[test]
+fn integral_floats_are_written_without_a_point() {
+    assert_eq!(output("2.0W"), "2\n");
+    assert_eq!(output("4√W"), "2\n");
+    assert_eq!(output("0.5W"), "0.5\n");
+    assert_eq!(output("¯2.5W"), "-2.5\n");
+    assert_eq!(output("(1 2.0 0.5)W"), "[1 2 0.5]\n");
+}
@@ -0,0 +1,44 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::error::{KatError, RuntimeError};
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+#[test]
+fn filter_keeps_truthy_items() {
+    assert_eq!(stack("(1 2 3 4)⊆2%$"), "(1 3)");
+    assert_eq!(stack("(0 1()(0)\"\")⊆NN$"), "(1 (0))");
+    assert_eq!(stack("()⊆1$"), "()");
+}
+
+#[test]
+fn filtering_a_string_gives_a_string() {
+    assert_eq!(stack("\"Hello\"⊆'l=N$"), "\"Heo\"");
+}
+
+#[test]
+fn fold_applies_the_block_from_the_left() {
+    assert_eq!(stack("(1 2 3 4),+$"), "10");
+    assert_eq!(stack("(1 2 3),-$"), "¯4");
+    assert_eq!(stack("(5),+$"), "5");
+    assert_eq!(stack("\"abc\",x+$"), "\"cba\"");
+}
+
+#[test]
+fn folding_nothing_fails() {
+    match run("(),+$", "", &Limits::default()) {
+        Err(KatError::Runtime(RuntimeError::EmptyFold, _)) => {}
+        result => panic!("{:?}", result.map(|out| render_stack(&out.stack))),
+    }
+}
+
+#[test]
+fn scan_collects_each_step() {
+    assert_eq!(stack("(1 2 3 4)\\+$"), "(1 3 6 10)");
+    assert_eq!(stack("()\\+$"), "()");
+    assert_eq!(stack("\"abc\"\\+$"), "(\"a\" \"ab\" \"abc\")");
+}
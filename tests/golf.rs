@@ -0,0 +1,48 @@
+extern crate katlang;
+
+use katlang::golf::golf;
+use katlang::judge::{parse_cases, run_case};
+use katlang::Limits;
+
+/// Golfs a program, checking rewrites against the cases.
+fn golfed(code: &str, cases: &str) -> String {
+    let cases = parse_cases(cases).unwrap();
+    let limits = Limits::default();
+    let passes = |code: &str| {
+        cases
+            .iter()
+            .all(|case| run_case(code, case, &limits).passed(case))
+    };
+    assert!(passes(code));
+    golf(code, passes).unwrap().code
+}
+
+#[test]
+fn repeated_literals_are_duplicated() {
+    assert_eq!(
+        golfed("RI1 1++W", "3\n---\n5\n===\n10\n---\n12\n"),
+        "RI1:++"
+    );
+}
+
+#[test]
+fn implicit_blocks_are_used() {
+    assert_eq!(golfed("(1 2 3)[1+]&$", "\n---\n[2 3 4]\n"), "(1 2 3)&1+");
+}
+
+#[test]
+fn repeated_blocks_are_moved_to_a_variable() {
+    assert_eq!(
+        golfed(
+            "(1 2)[2*1+2*1+]&$(3 4)[2*1+2*1+]&$+",
+            "\n---\n[7 11 15 19]\n"
+        ),
+        "{2*1+2*1+}a(1 2)<a&$(3 4)<a&$+"
+    );
+}
+
+#[test]
+fn rewrites_that_fail_the_cases_are_skipped() {
+    // Dropping the W would leave 1 unprinted.
+    assert_eq!(golfed("1 2W", "\n---\n2\n1\n"), "1 2W");
+}
@@ -0,0 +1,59 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::integer::Integer;
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+fn int(text: &str) -> Integer {
+    text.parse().unwrap()
+}
+
+#[test]
+fn values_that_fit_are_small() {
+    assert_eq!(int("9223372036854775807"), Integer::Small(i64::MAX));
+    assert_eq!(
+        int("9223372036854775808").sub(&Integer::from(1)),
+        Integer::Small(i64::MAX)
+    );
+    assert_eq!(int("-9223372036854775808").to_i64(), Some(i64::MIN));
+    assert_eq!(int("-9223372036854775809").to_i64(), None);
+}
+
+#[test]
+fn small_edge_cases_promote() {
+    let min = Integer::from(i64::MIN);
+    let minus_one = Integer::from(-1);
+    assert_eq!(min.floor_div(&minus_one), Some(int("9223372036854775808")));
+    assert_eq!(min.floor_mod(&minus_one), Some(Integer::from(0)));
+    assert_eq!(min.abs().to_string(), "9223372036854775808");
+    assert_eq!(Integer::from(3).pow(40).to_string(), "12157665459056928801");
+}
+
+#[test]
+fn programs_compute_with_big_integers() {
+    assert_eq!(stack("2 100^"), "1267650600228229401496703205376");
+    assert_eq!(stack("10 30^7/"), "142857142857142857142857142857");
+    assert_eq!(stack("10 30^7%"), "1");
+    assert_eq!(stack("2 100^±¦"), "1267650600228229401496703205376");
+    assert_eq!(stack("2 100^±§"), "¯1");
+    assert_eq!(stack("2 64^2 64^-"), "0");
+}
+
+#[test]
+fn fibonacci_doesnt_overflow() {
+    assert_eq!(stack("1:100#;+x$_"), "927372692193078999176");
+}
+
+#[test]
+fn strings_parse_to_big_integers() {
+    assert_eq!(
+        stack("\"123456789012345678901234567890\"I1+"),
+        "123456789012345678901234567891"
+    );
+    let out = run("2 70^W", "", &Limits::default()).unwrap();
+    assert_eq!(out.output, "1180591620717411303424\n");
+}
@@ -0,0 +1,43 @@
+extern crate katlang;
+
+use katlang::error::{KatError, RuntimeError};
+use katlang::{parse, run, CapturedOutput, Interpreter, Limits};
+use std::io;
+
+#[test]
+fn interpreters_on_stdin_can_coexist() {
+    let first = Interpreter::new(false);
+    let second = Interpreter::new(false);
+    drop((first, second));
+}
+
+#[test]
+fn programs_read_and_write_the_given_handles() {
+    let output = CapturedOutput::new();
+    let mut interpreter = Interpreter::with_io(
+        false,
+        Box::new(io::Cursor::new(b"first\r\nsecond".to_vec())),
+        Box::new(output.clone()),
+    );
+    let commands = parse("RWR\"!\"w").unwrap();
+    interpreter.execute(commands.iter()).unwrap();
+    assert_eq!(output.contents(), "first\n!");
+    assert_eq!(interpreter.main_stack.len(), 1);
+}
+
+#[test]
+fn reading_past_the_input_is_an_error() {
+    match run("RR", "only\n", &Limits::default()) {
+        Err(KatError::Runtime(RuntimeError::EndOfInput, Some(span))) => {
+            assert_eq!(span.column, 2)
+        }
+        result => panic!("{:?}", result.map(|output| output.stack)),
+    }
+}
+
+#[test]
+fn run_captures_the_output() {
+    let result = run("RI2*W", "21\n", &Limits::default()).unwrap();
+    assert_eq!(result.output, "42\n");
+    assert!(result.stack.is_empty());
+}
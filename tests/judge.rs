@@ -0,0 +1,44 @@
+extern crate katlang;
+
+use katlang::judge::{diff, parse_cases, run_case, TestCase};
+use katlang::Limits;
+
+#[test]
+fn cases_are_split_into_input_and_output() {
+    let cases = parse_cases("21\n---\n42\n===\n\n---\n\n===\n").unwrap();
+    assert_eq!(
+        cases,
+        vec![
+            TestCase {
+                line: 1,
+                input: "21\n".to_owned(),
+                expected: "42\n".to_owned(),
+            },
+            TestCase {
+                line: 5,
+                input: "\n".to_owned(),
+                expected: "\n".to_owned(),
+            },
+        ]
+    );
+    assert!(parse_cases("21\n42\n").is_err());
+}
+
+#[test]
+fn output_includes_the_top_of_the_stack() {
+    let cases = parse_cases("21\n---\n42\n===\n5\n---\n11\n===\nx\n---\n\n").unwrap();
+    let limits = Limits::default();
+    let results: Vec<_> = cases
+        .iter()
+        .map(|case| run_case("RI2*", case, &limits))
+        .collect();
+    assert!(results[0].passed(&cases[0]));
+    assert!(!results[1].passed(&cases[1]));
+    assert_eq!(results[1].output, "10\n");
+    assert!(results[2].error.is_some());
+}
+
+#[test]
+fn diff_marks_changed_lines() {
+    assert_eq!(diff("1\n2\n3\n", "1\n4\n"), "  1\n- 2\n+ 4\n- 3\n");
+}
@@ -0,0 +1,129 @@
+extern crate katlang;
+
+use katlang::error::{KatError, RuntimeError};
+use katlang::interpreter::DEFAULT_MAX_DEPTH;
+use katlang::{parse, run, CapturedOutput, CatValue, Interpreter, Limits};
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn runtime_error(code: &str, limits: &Limits) -> (RuntimeError, Option<usize>) {
+    match run(code, "", limits) {
+        Err(KatError::Runtime(e, span)) => (e, span.map(|span| span.column)),
+        result => panic!("{:?}", result.map(|output| output.stack)),
+    }
+}
+
+#[test]
+fn loops_over_empty_blocks_hit_the_step_limit() {
+    let limits = Limits {
+        max_steps: Some(1000),
+        ..Limits::default()
+    };
+    assert_eq!(
+        runtime_error("99999999999[]#$", &limits),
+        (RuntimeError::StepLimitExceeded(1000), Some(14))
+    );
+}
+
+#[test]
+fn loops_over_empty_blocks_time_out() {
+    let limits = Limits {
+        timeout: Some(Duration::from_millis(200)),
+        ..Limits::default()
+    };
+    let start = Instant::now();
+    assert_eq!(
+        runtime_error("99999999999[]#$", &limits),
+        (RuntimeError::Timeout, Some(14))
+    );
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+/// Runs a program on a thread with the stack of a typical main thread.
+fn on_main_sized_stack(code: &'static str, limits: Limits) -> (RuntimeError, Option<usize>) {
+    thread::Builder::new()
+        .stack_size(8 * 1024 * 1024)
+        .spawn(move || runtime_error(code, &limits))
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn deep_recursion_fails_cleanly_by_default() {
+    let limit = RuntimeError::DepthLimitExceeded(DEFAULT_MAX_DEPTH);
+    for code in &["[:!]:!", "[(1)&<v!$]>v<v!", "[(1 2)\\<v!$]>v<v!"] {
+        assert_eq!(on_main_sized_stack(code, Limits::default()).0, limit);
+    }
+}
+
+#[test]
+fn nested_lists_count_towards_the_depth() {
+    let limits = Limits {
+        max_depth: Some(2),
+        ..Limits::default()
+    };
+    assert!(run("((1))", "", &limits).is_ok());
+    assert_eq!(
+        runtime_error("(((1)))", &limits),
+        (RuntimeError::DepthLimitExceeded(2), Some(3))
+    );
+}
+
+#[test]
+fn every_command_counts_as_a_step() {
+    let limits = Limits {
+        max_steps: Some(3),
+        ..Limits::default()
+    };
+    assert!(run("1 2 3", "", &limits).is_ok());
+    assert_eq!(
+        runtime_error("1 2 3 4", &limits),
+        (RuntimeError::StepLimitExceeded(3), Some(7))
+    );
+}
+
+#[test]
+fn growing_values_hit_the_memory_limit() {
+    let limits = Limits {
+        max_memory: Some(1 << 20),
+        ..Limits::default()
+    };
+    assert!(run("\"ab\"10#:+", "", &limits).is_ok());
+    assert_eq!(
+        runtime_error("\"ab\"40#:+", &limits),
+        (RuntimeError::MemoryLimitExceeded(1 << 20), Some(8))
+    );
+}
+
+/// Values are only measured under a memory limit, since measuring a list
+/// walks all of it. Growing a list one item at a time stays linear.
+#[test]
+fn large_lists_are_cheap_without_a_memory_limit() {
+    let limits = Limits {
+        timeout: Some(Duration::from_secs(10)),
+        ..Limits::default()
+    };
+    match run("()50000#(1)+", "", &limits).unwrap().stack.as_slice() {
+        [CatValue::VStack(items)] => assert_eq!(items.len(), 50000),
+        stack => panic!("{:?}", stack),
+    }
+}
+
+#[test]
+fn setting_a_memory_limit_counts_existing_values() {
+    let mut interpreter = Interpreter::with_io(
+        false,
+        Box::new(io::empty()),
+        Box::new(CapturedOutput::new()),
+    );
+    interpreter
+        .execute(parse("\"ab\"20#:+$").unwrap().iter())
+        .unwrap();
+    interpreter.set_max_memory(Some(1 << 20));
+    match interpreter.execute(parse("1").unwrap().iter()) {
+        Err(KatError::Runtime(RuntimeError::MemoryLimitExceeded(_), _)) => {}
+        result => panic!("{:?}", result),
+    }
+}
@@ -0,0 +1,44 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::{run, Limits};
+
+fn stack(code: &str) -> String {
+    render_stack(&run(code, "", &Limits::default()).unwrap().stack)
+}
+
+#[test]
+fn sort_uses_the_total_ordering() {
+    assert_eq!(stack("(3 1 2)↑"), "(1 2 3)");
+    assert_eq!(stack("(2.5 1 ¯3 2)↑"), "(¯3 1 2 2.5)");
+    assert_eq!(stack("((2)(1 5)(1))↑"), "((1) (1 5) (2))");
+    assert_eq!(stack("(`+(0)\"b\"\"a\"1)↑"), "(1 \"a\" \"b\" (0) `+)");
+}
+
+#[test]
+fn sorting_a_string_sorts_its_characters() {
+    assert_eq!(stack("\"banana\"↑"), "\"aaabnn\"");
+}
+
+#[test]
+fn sort_by_is_stable() {
+    assert_eq!(stack("(3 1 2)B±$"), "(3 2 1)");
+    assert_eq!(stack("(5 2 4 1)B2%$"), "(2 4 5 1)");
+}
+
+#[test]
+fn group_collects_runs() {
+    assert_eq!(stack("(1 1 2 1)⊂"), "((1 1) (2) (1))");
+    assert_eq!(stack("(1 1 2 1)↑⊂"), "((1 1 1) (2))");
+    assert_eq!(stack("\"aabc\"⊂"), "(\"aa\" \"b\" \"c\")");
+    assert_eq!(stack("()⊂"), "()");
+}
+
+#[test]
+fn count_and_unique() {
+    assert_eq!(stack("(1 2 1 1)1¢"), "3");
+    assert_eq!(stack("((1)1(1))(1)¢"), "2");
+    assert_eq!(stack("\"banana\"'a¢"), "3");
+    assert_eq!(stack("(3 1 3 2 1)U"), "(3 1 2)");
+    assert_eq!(stack("\"banana\"U"), "\"ban\"");
+}
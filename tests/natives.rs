@@ -0,0 +1,81 @@
+extern crate katlang;
+
+use katlang::error::{KatError, RuntimeError};
+use katlang::{CapturedOutput, CatValue, Interpreter, Natives, Parser};
+use std::io;
+
+#[test]
+#[should_panic(expected = "Native command names can't start with '«'")]
+fn names_cant_start_a_raw_string() {
+    Natives::new().register("«x", |_| Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "Native command names can't start with '»'")]
+fn names_cant_end_a_raw_string() {
+    Natives::new().register("»", |_| Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "Native command names can't start with '¶'")]
+fn names_cant_start_a_comment() {
+    Natives::new().register("¶", |_| Ok(()));
+}
+
+fn natives() -> Natives {
+    let mut natives = Natives::new();
+    natives.register("d", |interpreter: &mut Interpreter| {
+        let value = interpreter.pop_res()?;
+        interpreter.push(value.clone());
+        interpreter.push(value);
+        Ok(())
+    });
+    natives.register("dd", |interpreter: &mut Interpreter| {
+        interpreter.push(CatValue::VString("dd".to_owned()));
+        Ok(())
+    });
+    natives.register("+", |_| Err(RuntimeError::EmptyStack));
+    natives
+}
+
+fn execute(code: &str) -> Result<Vec<CatValue>, KatError> {
+    let mut parser = Parser::with_natives(natives());
+    parser.parse(code)?;
+    let mut interpreter = Interpreter::with_io(
+        false,
+        Box::new(io::empty()),
+        Box::new(CapturedOutput::new()),
+    );
+    interpreter.execute(parser.commands.iter())?;
+    Ok(interpreter.main_stack)
+}
+
+#[test]
+fn natives_run_like_builtins() {
+    assert_eq!(execute("1d").unwrap().len(), 2);
+}
+
+#[test]
+fn the_longest_name_wins() {
+    assert_eq!(
+        execute("dd").unwrap(),
+        vec![CatValue::VString("dd".to_owned())]
+    );
+}
+
+#[test]
+fn natives_take_precedence_and_report_spans() {
+    match execute("1 2+") {
+        Err(KatError::Runtime(RuntimeError::EmptyStack, Some(span))) => {
+            assert_eq!(span.column, 4)
+        }
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn natives_show_their_name() {
+    let mut parser = Parser::with_natives(natives());
+    parser.parse("d").unwrap();
+    assert_eq!(format!("{:?}", parser.commands), "[Native(\"d\")]");
+}
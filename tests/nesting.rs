@@ -0,0 +1,47 @@
+extern crate katlang;
+
+use katlang::error::{KatError, ParseError};
+use katlang::parse;
+use katlang::parser::{Parser, MAX_NESTING};
+
+fn error(result: Result<impl std::fmt::Debug, KatError>) -> (ParseError, usize) {
+    match result {
+        Err(KatError::Parse(e, span)) => (e, span.column),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn nesting_up_to_the_limit_parses() {
+    let code = "[".repeat(MAX_NESTING) + &"]".repeat(MAX_NESTING);
+    assert!(parse(&code).is_ok());
+    let code = "(".repeat(MAX_NESTING) + &")".repeat(MAX_NESTING);
+    assert!(parse(&code).is_ok());
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let too_deep = (ParseError::TooDeeplyNested(MAX_NESTING), MAX_NESTING + 1);
+    assert_eq!(error(parse(&"[".repeat(2000))), too_deep);
+    assert_eq!(error(parse(&"(".repeat(2000))), too_deep);
+    assert_eq!(error(parse(&"&".repeat(2000))), too_deep);
+    assert_eq!(error(parse(&"`".repeat(2000))), too_deep);
+    let mut parser = Parser::new();
+    let too_deep = (
+        ParseError::TooDeeplyNested(MAX_NESTING),
+        5 + 6 * MAX_NESTING,
+    );
+    assert_eq!(
+        error(parser.parse_verbose(&"map { ".repeat(2000))),
+        too_deep
+    );
+}
+
+#[test]
+fn a_parser_can_be_reused_after_deep_nesting() {
+    let mut parser = Parser::new();
+    assert!(parser.parse(&"[".repeat(2000)).is_err());
+    let code = "[".repeat(MAX_NESTING) + &"]".repeat(MAX_NESTING);
+    assert!(Parser::new().parse(&code).is_ok());
+    assert!(parser.parse(&code).is_ok());
+}
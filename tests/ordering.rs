@@ -0,0 +1,40 @@
+extern crate katlang;
+
+use katlang::integer::Integer;
+use katlang::CatValue::{self, VFloat, VInteger};
+use std::cmp::Ordering::{Equal, Greater, Less};
+use std::f64;
+
+fn int(text: &str) -> CatValue {
+    VInteger(text.parse::<Integer>().unwrap())
+}
+
+#[test]
+fn zeros_are_equal() {
+    assert_eq!(VFloat(0.0).cmp(&VFloat(-0.0)), Equal);
+    assert_eq!(int("0").cmp(&VFloat(-0.0)), Equal);
+}
+
+#[test]
+fn integers_compare_exactly_with_floats() {
+    let float = VFloat(9007199254740992.0);
+    assert_eq!(int("9007199254740992").cmp(&float), Equal);
+    assert_eq!(int("9007199254740993").cmp(&float), Greater);
+    assert_eq!(float.cmp(&int("9007199254740993")), Less);
+    assert_eq!(int("3").cmp(&VFloat(3.5)), Less);
+    assert_eq!(int("-3").cmp(&VFloat(-3.5)), Greater);
+    assert_eq!(int("-4").cmp(&VFloat(-3.5)), Less);
+    let huge = int(&format!("1{}", "0".repeat(400)));
+    assert_eq!(huge.cmp(&VFloat(f64::MAX)), Greater);
+    assert_eq!(huge.cmp(&VFloat(f64::INFINITY)), Less);
+    assert_eq!(huge.cmp(&VFloat(f64::NEG_INFINITY)), Greater);
+}
+
+#[test]
+fn nan_sorts_after_every_number() {
+    let nan = VFloat(f64::NAN);
+    assert_eq!(nan.cmp(&VFloat(-f64::NAN)), Equal);
+    assert_eq!(nan.cmp(&VFloat(f64::INFINITY)), Greater);
+    assert_eq!(int("12345678901234567890123").cmp(&nan), Less);
+    assert_eq!(nan.cmp(&CatValue::VString(String::new())), Less);
+}
@@ -0,0 +1,81 @@
+//! Checks that the command reference generated from `BUILTINS` is correct,
+//! and that spec.md is up to date with it and with the code page.
+
+extern crate katlang;
+
+use katlang::builtins::{check_examples, reference, BUILTINS};
+use katlang::codepage::{decode, encode, score, table};
+use std::fs;
+
+#[test]
+fn examples_produce_documented_results() {
+    let failures = check_examples();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
+
+#[test]
+fn every_command_has_an_example() {
+    for info in BUILTINS {
+        assert!(!info.examples.is_empty(), "{} has no examples", info.name);
+    }
+}
+
+#[test]
+fn spec_contains_reference() {
+    let spec = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/spec.md")).unwrap();
+    assert!(
+        spec.contains(&reference()),
+        "spec.md is out of date, regenerate it with `katlang --doc`"
+    );
+}
+
+#[test]
+fn spec_contains_code_page() {
+    let spec = fs::read_to_string(concat!(env!("CARGO_MANIFEST_DIR"), "/spec.md")).unwrap();
+    assert!(
+        spec.contains(&table()),
+        "the code page table in spec.md is out of date"
+    );
+}
+
+#[test]
+fn code_page_round_trips() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    assert_eq!(encode(&decode(&bytes)), Ok(bytes));
+    assert_eq!(score("1:¯5+αW"), 7);
+    assert_eq!(score("a𝔸"), 5);
+}
+
+#[test]
+fn words_are_unique() {
+    for (i, info) in BUILTINS.iter().enumerate() {
+        assert!(
+            BUILTINS[..i].iter().all(|other| other.word != info.word),
+            "{} is used twice",
+            info.word
+        );
+    }
+}
+
+#[test]
+fn glyphs_are_single_bytes() {
+    for info in BUILTINS {
+        assert_eq!(score(&info.glyph.to_string()), 1, "{}", info.name);
+    }
+}
+
+/// Programs name variables with lowercase letters, so builtins only keep
+/// the ones they have always had.
+#[test]
+fn lowercase_letters_are_left_for_variables() {
+    for info in BUILTINS {
+        if info.glyph.is_ascii_lowercase() {
+            assert!(
+                "prwx".contains(info.glyph),
+                "{} takes {}",
+                info.name,
+                info.glyph
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+extern crate katlang;
+
+use katlang::error::{KatError, ParseError, RuntimeError};
+use katlang::{run, Limits};
+
+fn error(code: &str) -> KatError {
+    match run(code, "", &Limits::default()) {
+        Err(e) => e,
+        Ok(output) => panic!("{:?}", output.stack),
+    }
+}
+
+fn location(error: &KatError) -> (usize, usize) {
+    let span = error.span().unwrap();
+    (span.line, span.column)
+}
+
+#[test]
+fn parse_errors_point_at_the_culprit() {
+    let e = error("1 [2 3");
+    assert_eq!(
+        e,
+        KatError::Parse(ParseError::UnclosedBlock(']'), e.span().unwrap())
+    );
+    assert_eq!(location(&e), (1, 3));
+    let e = error("1 2]");
+    assert_eq!(
+        e,
+        KatError::Parse(ParseError::UnmatchedClose(']'), e.span().unwrap())
+    );
+    assert_eq!(location(&e), (1, 4));
+}
+
+#[test]
+fn runtime_errors_point_at_the_command() {
+    let e = error("1+");
+    assert_eq!(e, KatError::Runtime(RuntimeError::EmptyStack, e.span()));
+    assert_eq!(location(&e), (1, 2));
+}
+
+#[test]
+fn errors_inside_blocks_point_inside_the_block() {
+    assert_eq!(location(&error("[+]!")), (1, 2));
+    assert_eq!(location(&error("[\n+]!")), (2, 1));
+}
+
+#[test]
+fn rendering_marks_the_span() {
+    assert_eq!(
+        error("1 [+]!").render("1 [+]!"),
+        "Runtime error at 1:4: Pop from an empty stack\n1 [+]!\n   ^"
+    );
+}
@@ -0,0 +1,58 @@
+extern crate katlang;
+
+use katlang::error::{KatError, ParseError};
+use katlang::{parse, CatCommand};
+
+fn string(code: &str) -> String {
+    match parse(code).unwrap().as_slice() {
+        [command] => match &command.kind {
+            CatCommand::CreateString(v) => v.clone(),
+            kind => panic!("{:?}", kind),
+        },
+        commands => panic!("{:?}", commands),
+    }
+}
+
+fn error(code: &str) -> (ParseError, usize) {
+    match parse(code) {
+        Err(KatError::Parse(e, span)) => (e, span.column),
+        result => panic!("{:?}", result),
+    }
+}
+
+#[test]
+fn escapes_are_replaced() {
+    assert_eq!(string(r#""a\nb\tc\r""#), "a\nb\tc\r");
+    assert_eq!(string(r#""\\ \"""#), "\\ \"");
+    assert_eq!(string(r#""\u{41}\u{1F600}""#), "A😀");
+}
+
+#[test]
+fn raw_strings_have_no_escapes() {
+    assert_eq!(string(r#"«a\n"b»"#), "a\\n\"b");
+    assert_eq!(string("«»"), "");
+}
+
+#[test]
+fn unclosed_strings_are_errors() {
+    assert_eq!(error("1\"abc"), (ParseError::UnclosedString('"'), 2));
+    assert_eq!(error("\"abc\\"), (ParseError::UnclosedString('"'), 1));
+    assert_eq!(error("««"), (ParseError::UnclosedString('»'), 1));
+    assert_eq!(error("'"), (ParseError::UnexpectedEof, 2));
+}
+
+#[test]
+fn invalid_escapes_are_errors() {
+    assert_eq!(
+        error(r#""ab\q""#),
+        (ParseError::InvalidEscape("\\q".to_owned()), 4)
+    );
+    assert_eq!(
+        error(r#""\u{110000}""#),
+        (ParseError::InvalidEscape("\\u{110000}".to_owned()), 2)
+    );
+    assert_eq!(
+        error(r#""\u41""#),
+        (ParseError::InvalidEscape("\\u".to_owned()), 2)
+    );
+}
@@ -0,0 +1,86 @@
+extern crate katlang;
+
+use katlang::builtins::render_stack;
+use katlang::error::{KatError, ParseError};
+use katlang::{parse, parse_verbose, run, run_commands, Limits};
+
+fn same(verbose: &str, terse: &str) {
+    assert_eq!(parse_verbose(verbose).unwrap(), parse(terse).unwrap());
+}
+
+#[test]
+fn words_parse_like_glyphs() {
+    same("1 2 add dup mul", "1 2+:*");
+    same("read \" \" split", "R\" \"S");
+    same("(1 ¯2 3.5) sort", "(1¯2 3.5)↑");
+    same("'a 'b swap", "'a'bx");
+}
+
+#[test]
+fn implicit_blocks_are_written_in_braces() {
+    same("(1 2 3) map { 1 add }", "(1 2 3)&1+");
+    same("(1 2 3) [1 add] map", "(1 2 3)&1+");
+    same("(1 2 3) map {} drop", "(1 2 3)[]&$_");
+    same("[2 mul] (1 2) swap map", "[2*](1 2)x&$");
+    same("3 times { 1 } ", "3#1");
+}
+
+#[test]
+fn variables_take_a_name() {
+    same("[1 add] set v 2 call v get v", "{1+}v2v<v");
+    same("quote add quote map { 1 }", "`+`&1");
+}
+
+#[test]
+fn comments_are_skipped() {
+    same("¶ reads a number\nread int ¶ parse it\n2 mul", "RI2*");
+    assert_eq!(parse("5¶ five\n 6").unwrap(), parse("5 6").unwrap());
+    assert_eq!(parse("(1¶ a\n)").unwrap(), parse("(1)").unwrap());
+}
+
+#[test]
+fn errors_point_at_the_word() {
+    match parse_verbose("1 dupe") {
+        Err(KatError::Parse(ParseError::UnknownWord(word), span)) => {
+            assert_eq!(word, "dupe");
+            assert_eq!((span.column, span.len), (3, 4));
+        }
+        result => panic!("{:?}", result),
+    }
+    assert!(parse_verbose("quote call f").is_err());
+    assert!(parse_verbose("quote 5").is_err());
+    assert!(parse_verbose("map { 1").is_err());
+    assert!(parse_verbose("1 $").is_err());
+}
+
+/// Runs a verbose program and the terse one it should match, comparing
+/// their stacks and output.
+fn runs_like(verbose: &str, terse: &str) {
+    let limits = Limits::default();
+    let verbose = run_commands(&parse_verbose(verbose).unwrap(), "", &limits).unwrap();
+    let terse = run(terse, "", &limits).unwrap();
+    assert_eq!(render_stack(&verbose.stack), render_stack(&terse.stack));
+    assert_eq!(verbose.output, terse.output);
+}
+
+#[test]
+fn variables_run_like_in_terse_source() {
+    runs_like("[dup] set n 2 call n", "{:}n2n");
+    runs_like("[1 add] set a 5 call a call a", "{1+}a5aa");
+    runs_like("[0 eq] set s ¯3 call s write", "{0=}s¯3sw");
+    runs_like("3 set n [get n mul] set s 4 call s", "3>n{<n*}s4s");
+}
+
+#[test]
+fn variables_named_by_glyphs_run() {
+    let limits = Limits::default();
+    let commands = parse_verbose("[2 mul] set r 5 call r").unwrap();
+    let out = run_commands(&commands, "", &limits).unwrap();
+    assert_eq!(render_stack(&out.stack), "10");
+}
+
+#[test]
+fn unassigned_variables_fail_when_called() {
+    let commands = parse_verbose("call z").unwrap();
+    assert!(run_commands(&commands, "", &Limits::default()).is_err());
+}